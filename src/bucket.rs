@@ -0,0 +1,141 @@
+use chrono::prelude::*;
+use std::str::FromStr;
+
+/// Length of the columns of the detailed report
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Granularity {
+    Day,
+    /// Week starting at `Bucketing::week_start`, which is ISO week if it is Monday
+    Week,
+    Month,
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Granularity::Day),
+            "week" => Ok(Granularity::Week),
+            "month" => Ok(Granularity::Month),
+            _ => Err(format!("Unknown granularity: {}", s)),
+        }
+    }
+}
+
+/// Days from `first` to `last` both inclusive, which are clipped to the report period
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Bucket {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+/// Divides the report period into buckets of the granularity
+///
+/// The default divides it by day, where weeks start on Monday.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bucketing {
+    pub granularity: Granularity,
+    pub week_start: Weekday,
+}
+
+impl Default for Bucketing {
+    fn default() -> Self {
+        Bucketing {
+            granularity: Granularity::Day,
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+impl Bucketing {
+    /// Parses the first day of week, either Monday or Sunday
+    pub fn parse_week_start(s: &str) -> Result<Weekday, String> {
+        match s.to_lowercase().as_str() {
+            "monday" | "mon" => Ok(Weekday::Mon),
+            "sunday" | "sun" => Ok(Weekday::Sun),
+            _ => Err(format!("Week must start on Monday or Sunday: {}", s)),
+        }
+    }
+
+    /// Returns the first day of the whole bucket containing `date`
+    fn start_of(&self, date: &NaiveDate) -> NaiveDate {
+        match self.granularity {
+            Granularity::Day => *date,
+            Granularity::Week => {
+                let days = (date.weekday().num_days_from_monday() + 7
+                    - self.week_start.num_days_from_monday())
+                    % 7;
+                *date - chrono::Duration::days(i64::from(days))
+            }
+            Granularity::Month => NaiveDate::from_ymd(date.year(), date.month(), 1),
+        }
+    }
+
+    /// Returns the first day of the bucket containing `date`, which is not before `begin_date`
+    pub fn first_day(&self, date: &NaiveDate, begin_date: &NaiveDate) -> NaiveDate {
+        std::cmp::max(self.start_of(date), *begin_date)
+    }
+
+    /// Returns the column label of `bucket` by the start of its whole week or month
+    ///
+    /// e.g. "2020-12-01" for a day, "2020-W49" for an ISO week, "2020-11-29" for a week starting on Sunday,
+    /// or "2020-12" for a month, whether or not the bucket is clipped to the report period
+    pub fn label(&self, bucket: &Bucket) -> String {
+        let start = self.start_of(&bucket.first);
+        match self.granularity {
+            Granularity::Day => start.format("%Y-%m-%d").to_string(),
+            Granularity::Week if self.week_start == Weekday::Mon => {
+                start.format("%G-W%V").to_string()
+            }
+            Granularity::Week => start.format("%Y-%m-%d").to_string(),
+            Granularity::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucketing(granularity: Granularity, week_start: Weekday) -> Bucketing {
+        Bucketing {
+            granularity,
+            week_start,
+        }
+    }
+
+    #[test]
+    fn label_must_name_weeks_and_months_by_their_start() {
+        let week = bucketing(Granularity::Week, Weekday::Mon);
+        let sunday_week = bucketing(Granularity::Week, Weekday::Sun);
+        let month = bucketing(Granularity::Month, Weekday::Mon);
+        let bucket = |first: (u32, u32), last: (u32, u32)| Bucket {
+            first: NaiveDate::from_ymd(2020, first.0, first.1),
+            last: NaiveDate::from_ymd(2020, last.0, last.1),
+        };
+
+        assert_eq!(week.label(&bucket((12, 7), (12, 13))), "2020-W50");
+        assert_eq!(week.label(&bucket((12, 8), (12, 13))), "2020-W50");
+        assert_eq!(week.label(&bucket((12, 13), (12, 13))), "2020-W50");
+        assert_eq!(sunday_week.label(&bucket((12, 6), (12, 12))), "2020-12-06");
+        assert_eq!(sunday_week.label(&bucket((12, 12), (12, 12))), "2020-12-06");
+        assert_eq!(month.label(&bucket((2, 1), (2, 29))), "2020-02");
+        assert_eq!(month.label(&bucket((2, 29), (2, 29))), "2020-02");
+    }
+
+    #[test]
+    fn first_day_must_not_be_before_the_period() {
+        let week = bucketing(Granularity::Week, Weekday::Mon);
+        let begin = NaiveDate::from_ymd(2020, 12, 2);
+
+        assert_eq!(
+            week.first_day(&NaiveDate::from_ymd(2020, 12, 3), &begin),
+            begin
+        );
+        assert_eq!(
+            week.first_day(&NaiveDate::from_ymd(2020, 12, 10), &begin),
+            NaiveDate::from_ymd(2020, 12, 7)
+        );
+    }
+}
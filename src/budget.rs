@@ -0,0 +1,228 @@
+use crate::toggl::RecordKey;
+use crate::values::{Duration, Period};
+use chrono::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Hours budgeted for a project, either in total since a date or per calendar month
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ProjectBudget {
+    pub hours: f64,
+    /// First day of the total budget
+    pub since: Option<NaiveDate>,
+    /// If true, the budget is renewed every month
+    #[serde(default)]
+    pub monthly: bool,
+}
+
+/// Budgets of projects, read from a JSON file like
+///
+/// ```json
+/// {
+///   "thresholds": [80, 100],
+///   "projects": {
+///     "ProjectA": {"hours": 100, "since": "2020-10-01"},
+///     "ProjectB": {"hours": 20, "monthly": true}
+///   }
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Budgets {
+    /// Percentages of consumed hours which are warned
+    #[serde(default = "Budgets::default_thresholds")]
+    pub thresholds: Vec<u64>,
+    /// Budgets by project name
+    pub projects: BTreeMap<String, ProjectBudget>,
+}
+
+/// Consumption of a budget until the end of the report period
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BudgetStatus {
+    pub project: String,
+    pub monthly: bool,
+    /// Days counted into the budget
+    pub period: Period,
+    pub budget: Duration,
+    pub consumed: Duration,
+    /// Consumed hours in percent of the budget, which is rounded
+    pub percent: u64,
+    /// Day when the budget runs out at the average rate in `period`
+    ///
+    /// None if it is already used up, nothing is consumed, or a monthly budget lasts the month.
+    pub exhaustion: Option<NaiveDate>,
+    /// The highest threshold which the consumption reached
+    pub threshold: Option<u64>,
+}
+
+impl Budgets {
+    fn default_thresholds() -> Vec<u64> {
+        vec![80, 100]
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let budgets: Budgets =
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (name, budget) in &budgets.projects {
+            if budget.monthly == budget.since.is_some() {
+                return Err(format!(
+                    "{}: the budget of {} must have either since or monthly",
+                    path.display(),
+                    name
+                ));
+            }
+        }
+        Ok(budgets)
+    }
+
+    /// Returns the days of `budget` until `end_date`
+    fn budget_period(budget: &ProjectBudget, end_date: &NaiveDate) -> Period {
+        let begin = match budget.since {
+            Some(since) if !budget.monthly => since,
+            _ => NaiveDate::from_ymd(end_date.year(), end_date.month(), 1),
+        };
+        Period::new(std::cmp::min(begin, *end_date), *end_date)
+    }
+
+    /// Returns the days needed to compute the budgets until `end_date`
+    pub fn fetch_period(&self, end_date: &NaiveDate) -> Period {
+        let begin = self
+            .projects
+            .values()
+            .map(|budget| Self::budget_period(budget, end_date).begin)
+            .min()
+            .unwrap_or(*end_date);
+        Period::new(begin, *end_date)
+    }
+
+    /// Sums up `records` into each budget until `end_date`
+    ///
+    /// `records` must cover `fetch_period`.
+    pub fn status(
+        &self,
+        records: &[(RecordKey, Duration)],
+        end_date: &NaiveDate,
+    ) -> Vec<BudgetStatus> {
+        self.projects
+            .iter()
+            .map(|(name, budget)| {
+                let period = Self::budget_period(budget, end_date);
+                let consumed = records
+                    .iter()
+                    .filter(|(key, _)| period.contains(&key.date))
+                    .filter(|(key, _)| key.project.value.as_ref().map(|p| &p.value) == Some(name))
+                    .fold(Duration::new(0), |acc, (_, dur)| acc + *dur);
+                let hours = Duration::new((budget.hours * 3_600_000.0).round() as u64);
+                // a budget of 0h is used up from the beginning
+                let percent = (consumed.value * 100 + hours.value / 2)
+                    .checked_div(hours.value)
+                    .unwrap_or(100);
+                BudgetStatus {
+                    project: name.to_string(),
+                    monthly: budget.monthly,
+                    exhaustion: Self::exhaustion(budget, &period, &hours, &consumed),
+                    period,
+                    budget: hours,
+                    consumed,
+                    percent,
+                    threshold: self
+                        .thresholds
+                        .iter()
+                        .filter(|threshold| percent >= **threshold)
+                        .max()
+                        .copied(),
+                }
+            })
+            .collect()
+    }
+
+    fn exhaustion(
+        budget: &ProjectBudget,
+        period: &Period,
+        hours: &Duration,
+        consumed: &Duration,
+    ) -> Option<NaiveDate> {
+        if consumed.value == 0 || consumed.value >= hours.value {
+            return None;
+        }
+        let days = (period.end - period.begin).num_days() as u64 + 1;
+        // days to consume the rest at the average rate per day, rounded up
+        let rest = u128::from(hours.value - consumed.value) * u128::from(days);
+        let consumed = u128::from(consumed.value);
+        let needed = ((rest + consumed - 1) / consumed) as i64;
+        let date = period.end + chrono::Duration::days(needed);
+        if budget.monthly && date.month() != period.end.month() {
+            return None;
+        }
+        Some(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::{Project, User};
+
+    fn record(project: &str, date: NaiveDate, hours: u64) -> (RecordKey, Duration) {
+        (
+            RecordKey {
+                user: User::new("Alice"),
+                project: Project::new(Some(project)),
+                date,
+            },
+            Duration::new(hours * 3_600_000),
+        )
+    }
+
+    #[test]
+    fn status_must_project_the_exhaustion_at_the_average_rate() {
+        let budgets: Budgets = serde_json::from_str(
+            r#"{"projects": {
+                "ProjectA": {"hours": 100, "since": "2020-11-01"},
+                "ProjectB": {"hours": 20, "monthly": true}
+            }}"#,
+        )
+        .unwrap();
+        let end_date = NaiveDate::from_ymd(2020, 12, 10);
+        let records = vec![
+            record("ProjectA", NaiveDate::from_ymd(2020, 10, 31), 50),
+            record("ProjectA", NaiveDate::from_ymd(2020, 11, 1), 40),
+            record("ProjectA", NaiveDate::from_ymd(2020, 12, 10), 40),
+            record("ProjectB", NaiveDate::from_ymd(2020, 11, 30), 50),
+            record("ProjectB", NaiveDate::from_ymd(2020, 12, 1), 5),
+        ];
+
+        assert_eq!(
+            budgets.fetch_period(&end_date).begin,
+            NaiveDate::from_ymd(2020, 11, 1)
+        );
+        let actual = budgets.status(&records, &end_date);
+        // 80h in 40 days runs out 20h in 10 days, while 5h in 10 days does not run out 15h in December
+        let expected = vec![
+            BudgetStatus {
+                project: "ProjectA".to_string(),
+                monthly: false,
+                period: Period::new(NaiveDate::from_ymd(2020, 11, 1), end_date),
+                budget: Duration::new(100 * 3_600_000),
+                consumed: Duration::new(80 * 3_600_000),
+                percent: 80,
+                exhaustion: Some(NaiveDate::from_ymd(2020, 12, 20)),
+                threshold: Some(80),
+            },
+            BudgetStatus {
+                project: "ProjectB".to_string(),
+                monthly: true,
+                period: Period::new(NaiveDate::from_ymd(2020, 12, 1), end_date),
+                budget: Duration::new(20 * 3_600_000),
+                consumed: Duration::new(5 * 3_600_000),
+                percent: 25,
+                exhaustion: None,
+                threshold: None,
+            },
+        ];
+        assert_eq!(actual, expected);
+    }
+}
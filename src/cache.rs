@@ -0,0 +1,236 @@
+use chrono::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// FNV-1a, which is stable across Rust versions unlike `DefaultHasher`
+pub(crate) fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |acc, b| {
+        (acc ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Identifies a cached response of Toggl API
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheKey {
+    pub workspace: String,
+    pub endpoint: String,
+    /// Query parameters except the dates and paging
+    pub query: String,
+    /// The day which the response covers, or the last day for the range of days
+    pub day: NaiveDate,
+}
+
+/// On-disk cache of Toggl API responses
+///
+/// Responses for days before yesterday are kept indefinitely because those days are closed,
+/// while the others expire after `ttl`.
+/// Yesterday is not treated as closed since it may be today in the timezone of the Toggl profile.
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    pub dir: PathBuf,
+    pub ttl: chrono::Duration,
+    /// Today in the timezone of the report, which tells the closed days
+    pub today: NaiveDate,
+}
+
+impl ResponseCache {
+    /// Endpoints whose responses are cached, which are the directories under each workspace
+    const ENDPOINTS: [&'static str; 2] = ["summary", "details"];
+
+    pub fn new(dir: PathBuf, ttl: chrono::Duration, today: NaiveDate) -> Self {
+        ResponseCache { dir, ttl, today }
+    }
+
+    /// Returns `$XDG_CACHE_HOME/toggl2slack`, or `~/.cache/toggl2slack` if it is not set
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("toggl2slack")
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        self.dir
+            .join(&key.workspace)
+            .join(&key.endpoint)
+            .join(format!("{:016x}", fnv1a(&key.query)))
+            .join(format!("{}.json", key.day.format("%Y-%m-%d")))
+    }
+
+    fn is_fresh(&self, day: &NaiveDate, today: &NaiveDate, age: chrono::Duration) -> bool {
+        *day < today.pred() || age <= self.ttl
+    }
+
+    /// Returns the cached response if it has not expired
+    pub fn get(&self, key: &CacheKey) -> Option<String> {
+        let path = self.path(key);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        let age = chrono::Duration::from_std(age).ok()?;
+        if !self.is_fresh(&key.day, &self.today, age) {
+            return None;
+        }
+        fs::read_to_string(path).ok()
+    }
+
+    pub fn put(&self, key: &CacheKey, body: &str) -> io::Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, body)
+    }
+
+    /// Removes all the cached responses and the directories left empty
+    ///
+    /// Only the files laid out by `path` are removed, so that other files are kept
+    /// even if `dir` is not a cache directory like the home directory.
+    pub fn clear(&self) -> io::Result<()> {
+        // responses without workspace are right under `dir`
+        let mut workspace_dirs: Vec<PathBuf> = Self::subdirs(&self.dir)?
+            .into_iter()
+            .filter(|(name, _)| !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()))
+            .map(|(_, path)| path)
+            .collect();
+        workspace_dirs.push(self.dir.clone());
+        for workspace_dir in workspace_dirs {
+            for endpoint in Self::ENDPOINTS.iter() {
+                let endpoint_dir = workspace_dir.join(endpoint);
+                for (name, query_dir) in Self::subdirs(&endpoint_dir)? {
+                    let is_hash = name.len() == 16 && name.bytes().all(|b| b.is_ascii_hexdigit());
+                    if is_hash {
+                        Self::remove_responses(&query_dir)?;
+                    }
+                }
+                Self::remove_if_empty(&endpoint_dir);
+            }
+            Self::remove_if_empty(&workspace_dir);
+        }
+        Ok(())
+    }
+
+    /// Returns the names and paths of the subdirectories, which are none if `dir` does not exist
+    fn subdirs(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+        let entries = match fs::read_dir(dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            res => res?,
+        };
+        let mut subdirs = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                subdirs.push((name, entry.path()));
+            }
+        }
+        Ok(subdirs)
+    }
+
+    /// Removes the files named like `2020-12-01.json` in `dir`
+    fn remove_responses(dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_response = path.extension().map(|ext| ext == "json").unwrap_or(false)
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").is_ok())
+                    .unwrap_or(false);
+            if is_response && path.is_file() {
+                fs::remove_file(&path)?;
+            }
+        }
+        Self::remove_if_empty(dir);
+        Ok(())
+    }
+
+    fn remove_if_empty(dir: &Path) {
+        // fails if other files are left, which is fine
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str) -> ResponseCache {
+        let dir =
+            std::env::temp_dir().join(format!("toggl2slack-test-{}-{}", name, std::process::id()));
+        ResponseCache::new(
+            dir,
+            chrono::Duration::minutes(10),
+            NaiveDate::from_ymd(2020, 12, 10),
+        )
+    }
+
+    fn key(day: NaiveDate) -> CacheKey {
+        CacheKey {
+            workspace: "100".to_string(),
+            endpoint: "details".to_string(),
+            query: "billable=yes".to_string(),
+            day,
+        }
+    }
+
+    #[test]
+    fn is_fresh_must_keep_days_before_yesterday_indefinitely() {
+        let cache = cache("fresh");
+        let today = NaiveDate::from_ymd(2020, 12, 10);
+        let old = chrono::Duration::days(30);
+
+        assert!(cache.is_fresh(&NaiveDate::from_ymd(2020, 12, 8), &today, old));
+        assert!(!cache.is_fresh(&NaiveDate::from_ymd(2020, 12, 9), &today, old));
+        assert!(cache.is_fresh(
+            &NaiveDate::from_ymd(2020, 12, 9),
+            &today,
+            chrono::Duration::minutes(5)
+        ));
+    }
+
+    #[test]
+    fn get_must_return_what_put_stores_until_clear() {
+        let cache = cache("roundtrip");
+        let key = key(NaiveDate::from_ymd(2020, 12, 1));
+
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, "[]").unwrap();
+        assert_eq!(cache.get(&key), Some("[]".to_string()));
+        cache.clear().unwrap();
+        assert_eq!(cache.get(&key), None);
+        assert!(!cache.dir.exists());
+    }
+
+    #[test]
+    fn clear_must_keep_files_which_the_cache_did_not_create() {
+        let cache = cache("foreign");
+        let key = key(NaiveDate::from_ymd(2020, 12, 1));
+        cache.put(&key, "[]").unwrap();
+        let foreign = [
+            cache.dir.join("notes.txt"),
+            cache.dir.join("100").join("details").join("notes.json"),
+            cache
+                .dir
+                .join("projects")
+                .join("details")
+                .join("2020-12-01.json"),
+        ];
+        for path in &foreign {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "keep").unwrap();
+        }
+
+        cache.clear().unwrap();
+
+        assert_eq!(cache.get(&key), None);
+        for path in &foreign {
+            assert!(path.exists(), "{}", path.display());
+        }
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
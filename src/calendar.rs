@@ -0,0 +1,183 @@
+use crate::locale::Labels;
+use chrono::prelude::*;
+use ical::IcalParser;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Weekends and holidays, when no hours are expected
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calendar {
+    pub weekends: Vec<Weekday>,
+    /// Names of holidays by date
+    pub holidays: BTreeMap<NaiveDate, String>,
+}
+
+impl Default for Calendar {
+    /// Returns Saturday and Sunday without holidays
+    fn default() -> Self {
+        Calendar {
+            weekends: vec![Weekday::Sat, Weekday::Sun],
+            holidays: BTreeMap::new(),
+        }
+    }
+}
+
+impl Calendar {
+    /// Name of the bundled set of Japanese national holidays
+    pub const JAPAN: &'static str = "jp";
+
+    /// Parses comma-separated days of week, e.g. "sat,sun"
+    pub fn parse_weekends(s: &str) -> Result<Vec<Weekday>, String> {
+        s.split(',')
+            .map(|d| d.trim())
+            .filter(|d| !d.is_empty())
+            .map(|d| {
+                d.parse::<Weekday>()
+                    .map_err(|_| format!("Unknown day of week: {}", d))
+            })
+            .collect()
+    }
+
+    /// Adds holidays of the bundled set, an iCalendar file (.ics) or a CSV file of `date,name`
+    pub fn add_holidays(&mut self, source: &str) -> Result<(), String> {
+        let holidays = if source == Self::JAPAN {
+            Self::parse_csv(include_str!("../calendars/jp.csv"))?
+        } else {
+            let path = Path::new(source);
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let is_ical = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("ics"))
+                .unwrap_or(false);
+            if is_ical {
+                Self::parse_ical(&text)
+            } else {
+                Self::parse_csv(&text)
+            }
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+        self.holidays.extend(holidays);
+        Ok(())
+    }
+
+    /// Parses lines of `2020-12-31,name`, where the name is optional and `#` starts a comment
+    fn parse_csv(text: &str) -> Result<BTreeMap<NaiveDate, String>, String> {
+        text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut fields = line.splitn(2, ',');
+                let date = fields.next().unwrap_or("");
+                let name = fields.next().map(|name| name.trim()).unwrap_or("");
+                let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                    .map_err(|e| format!("Invalid date {}: {}", date, e))?;
+                Ok((date, name.to_string()))
+            })
+            .collect()
+    }
+
+    /// Takes every day of the events, where DTEND of all-day events is exclusive
+    fn parse_ical(text: &str) -> Result<BTreeMap<NaiveDate, String>, String> {
+        let date_of = |value: &str| {
+            NaiveDate::parse_from_str(value.get(..8).unwrap_or(value), "%Y%m%d")
+                .map_err(|e| format!("Invalid date {}: {}", value, e))
+        };
+        let mut holidays = BTreeMap::new();
+        for calendar in IcalParser::new(BufReader::new(text.as_bytes())) {
+            let calendar = calendar.map_err(|e| e.to_string())?;
+            for event in calendar.events {
+                let property = |name: &str| {
+                    event
+                        .properties
+                        .iter()
+                        .find(|p| p.name == name)
+                        .and_then(|p| p.value.clone())
+                };
+                let start = match property("DTSTART") {
+                    Some(value) => date_of(&value)?,
+                    None => continue,
+                };
+                let end = match property("DTEND") {
+                    Some(value) => std::cmp::max(date_of(&value)?.pred(), start),
+                    None => start,
+                };
+                let name = property("SUMMARY")
+                    .unwrap_or_default()
+                    .replace("\\,", ",")
+                    .replace("\\;", ";");
+                for date in start.iter_days().take_while(|date| date <= &end) {
+                    holidays.insert(date, name.clone());
+                }
+            }
+        }
+        Ok(holidays)
+    }
+
+    pub fn is_day_off(&self, date: &NaiveDate) -> bool {
+        self.holidays.contains_key(date) || self.weekends.contains(&date.weekday())
+    }
+
+    /// Returns the name of the holiday, or the label of weekends
+    pub fn day_off_label<'a>(&'a self, date: &NaiveDate, labels: &'a Labels) -> Option<&'a str> {
+        match self.holidays.get(date) {
+            Some(name) if !name.is_empty() => Some(name),
+            Some(_) => Some(labels.holiday),
+            None if self.weekends.contains(&date.weekday()) => Some(labels.weekend),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    #[test]
+    fn parse_ical_must_take_every_day_of_all_day_events() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+            BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20201229\r\nDTEND;VALUE=DATE:20210101\r\nSUMMARY:Year-end\\, closed\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nDTSTART:20210104T000000Z\r\nSUMMARY:Kickoff\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let actual = Calendar::parse_ical(ics).unwrap();
+        let expected: BTreeMap<NaiveDate, String> = vec![
+            (NaiveDate::from_ymd(2020, 12, 29), "Year-end, closed"),
+            (NaiveDate::from_ymd(2020, 12, 30), "Year-end, closed"),
+            (NaiveDate::from_ymd(2020, 12, 31), "Year-end, closed"),
+            (NaiveDate::from_ymd(2021, 1, 4), "Kickoff"),
+        ]
+        .into_iter()
+        .map(|(date, name)| (date, name.to_string()))
+        .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn day_off_label_must_name_holidays_and_weekends() {
+        let mut calendar = Calendar::default();
+        calendar.add_holidays(Calendar::JAPAN).unwrap();
+        let labels = Locale::En.labels();
+
+        assert_eq!(
+            calendar.day_off_label(&NaiveDate::from_ymd(2021, 1, 11), labels),
+            Some("成人の日")
+        );
+        assert_eq!(
+            calendar.day_off_label(&NaiveDate::from_ymd(2021, 1, 9), labels),
+            Some("weekend")
+        );
+        assert_eq!(
+            calendar.day_off_label(&NaiveDate::from_ymd(2021, 1, 12), labels),
+            None
+        );
+        assert_eq!(
+            Calendar::parse_weekends("fri, Sat"),
+            Ok(vec![Weekday::Fri, Weekday::Sat])
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use crate::toggl::TogglDetail;
+use crate::values::{Project, User};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UserInfo {
+    pub id: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProjectInfo {
+    pub id: Option<u64>,
+    pub client: Option<String>,
+}
+
+/// IDs and clients of the users and projects which appear in a report
+///
+/// Summary reports and `RecordKey` only have names,
+/// so this is used to look up the rest of the attributes.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Catalog {
+    pub users: BTreeMap<User, UserInfo>,
+    pub projects: BTreeMap<Project, ProjectInfo>,
+}
+
+impl Catalog {
+    /// Collects users and projects from detailed report fetched from Toggl API
+    pub fn from_details(data: &[TogglDetail]) -> Self {
+        let mut catalog = Catalog::default();
+        for d in data {
+            catalog
+                .users
+                .entry(d.user.clone())
+                .or_insert(UserInfo { id: d.uid });
+            catalog
+                .projects
+                .entry(d.project.clone())
+                .or_insert(ProjectInfo {
+                    id: d.pid,
+                    client: d.client.clone(),
+                });
+        }
+        catalog
+    }
+
+    pub fn user(&self, user: &User) -> UserInfo {
+        self.users.get(user).cloned().unwrap_or_default()
+    }
+
+    pub fn project(&self, project: &Project) -> ProjectInfo {
+        self.projects.get(project).cloned().unwrap_or_default()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::values::Duration;
+use unicode_width::UnicodeWidthStr;
+
+/// Draws proportional bars of durations in monospaced text
+///
+/// e.g. with `width` 8, 5.5h of 8h is drawn as "█████▌"
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BarChart {
+    /// Number of cells of the longest bar
+    pub width: usize,
+}
+
+impl Default for BarChart {
+    fn default() -> Self {
+        BarChart { width: 20 }
+    }
+}
+
+impl BarChart {
+    /// Blocks of 1/8 to 7/8 cells
+    const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    const FULL_BLOCK: char = '█';
+
+    /// Returns the bar of `dur` relative to `max`, which is drawn in `width` cells
+    ///
+    /// The bar is padded with spaces, so that texts after bars are aligned.
+    pub fn bar(&self, dur: &Duration, max: &Duration) -> String {
+        if max.value == 0 {
+            return " ".repeat(self.width);
+        }
+        let eighths = (u128::from(dur.value) * self.width as u128 * 8 + u128::from(max.value) / 2)
+            / u128::from(max.value);
+        let eighths = eighths as usize;
+        let (full, partial) = (eighths / 8, eighths % 8);
+        let mut bar = Self::FULL_BLOCK.to_string().repeat(full);
+        let mut cells = full;
+        if partial > 0 {
+            bar.push(Self::PARTIAL_BLOCKS[partial - 1]);
+            cells += 1;
+        }
+        bar + &" ".repeat(self.width.saturating_sub(cells))
+    }
+
+    /// Pads `labels` with spaces up to the widest one on monospaced display
+    ///
+    /// CJK characters are counted as 2 cells.
+    pub fn pad_labels(labels: &[String]) -> Vec<String> {
+        let width = labels
+            .iter()
+            .map(|l| UnicodeWidthStr::width(l.as_str()))
+            .max()
+            .unwrap_or(0);
+        labels
+            .iter()
+            .map(|l| {
+                format!(
+                    "{}{}",
+                    l,
+                    " ".repeat(width - UnicodeWidthStr::width(l.as_str()))
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(h: f64) -> Duration {
+        Duration::new((h * 3_600_000.0) as u64)
+    }
+
+    #[test]
+    fn bar_must_be_proportional_in_eighths() {
+        let chart = BarChart { width: 8 };
+
+        assert_eq!(chart.bar(&hours(5.5), &hours(8.0)), "█████▌  ");
+        assert_eq!(chart.bar(&hours(8.0), &hours(8.0)), "████████");
+        assert_eq!(chart.bar(&hours(0.0), &hours(8.0)), "        ");
+        assert_eq!(chart.bar(&hours(1.0), &hours(0.0)), "        ");
+    }
+
+    #[test]
+    fn pad_labels_must_count_cjk_characters_as_two_cells() {
+        let labels = vec!["開発".to_string(), "Review".to_string(), "QA".to_string()];
+
+        assert_eq!(
+            BarChart::pad_labels(&labels),
+            vec!["開発  ", "Review", "QA    "]
+        );
+    }
+}
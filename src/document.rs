@@ -0,0 +1,452 @@
+use crate::locale::Locale;
+use crate::plot::Rgb;
+use crate::template::{ProjectContext, SummaryContext};
+use crate::values::Duration;
+use std::str::FromStr;
+
+/// Format of the report written by `--format`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Messages posted to Slack
+    Slack,
+    /// Standalone HTML page
+    Html,
+    /// GitHub-flavoured Markdown
+    Markdown,
+    /// Aggregated values in the schema of `JsonReport`
+    Json,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Slack
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "slack" => Ok(OutputFormat::Slack),
+            "html" => Ok(OutputFormat::Html),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+/// Summary and detailed table of a report, rendered into a document to archive
+///
+/// The values are aggregated by `MessageCreator` in the same way as the Slack messages.
+pub struct Document<'a> {
+    pub summary: &'a SummaryContext,
+    /// Cells of the detailed CSV including the header
+    pub detailed: &'a [Vec<String>],
+    pub locale: Locale,
+}
+
+impl<'a> Document<'a> {
+    /// Width of the longest bar in pixels
+    const BAR_WIDTH: u64 = 200;
+    const STYLE: &'static str = "body{font-family:sans-serif;margin:2em}\
+        table{border-collapse:collapse;margin-bottom:1.5em}\
+        th,td{border:1px solid #ccc;padding:.25em .5em}\
+        td.number{text-align:right}\
+        tr.description td{color:#666}\
+        tfoot td{font-weight:bold}";
+
+    fn title(&self) -> String {
+        format!(
+            "{} {}-{}",
+            self.summary.labels.summary_title,
+            self.locale.format_date(&self.summary.begin),
+            self.locale.format_date(&self.summary.end)
+        )
+    }
+
+    /// Returns the longest time of all the users, which the bars of users are relative to
+    fn users_max(&self) -> Duration {
+        self.summary
+            .workspaces
+            .iter()
+            .flat_map(|w| w.users.iter())
+            .flat_map(|u| u.projects.iter())
+            .map(|p| p.duration)
+            .max_by_key(|dur| dur.value)
+            .unwrap_or_default()
+    }
+
+    fn project_name(&self, project: &ProjectContext) -> String {
+        if project.running {
+            format!("{} ({})", project.name, self.summary.labels.running)
+        } else {
+            project.name.clone()
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let labels = &self.summary.labels;
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            lang = self.locale.tag(),
+            title = escape_html(&self.title()),
+            style = Self::STYLE,
+        );
+        let header = self.html_header(&["", labels.project, labels.hours, labels.share]);
+        let users_max = self.users_max();
+        for workspace in &self.summary.workspaces {
+            if !workspace.name.is_empty() {
+                html += &format!("<h2>{}</h2>\n", escape_html(&workspace.name));
+            }
+            for user in &workspace.users {
+                html += &format!(
+                    "<h3>{}</h3>\n<table>\n{}<tbody>\n",
+                    escape_html(&user.name),
+                    header
+                );
+                for project in &user.projects {
+                    html += &self.html_row(
+                        "",
+                        &[
+                            self.svg_bar(&project.name, &project.duration, &users_max),
+                            escape_html(&self.project_name(project)),
+                        ],
+                        &[project.time.clone(), project.share.clone()],
+                        &project.delta,
+                    );
+                    for description in &project.descriptions {
+                        html += &self.html_row(
+                            " class=\"description\"",
+                            &[
+                                String::new(),
+                                format!("&nbsp;&nbsp;{}", escape_html(&description.name)),
+                            ],
+                            &[description.time.clone(), String::new()],
+                            "",
+                        );
+                    }
+                }
+                html += &format!(
+                    "</tbody>\n<tfoot>\n{}</tfoot>\n</table>\n",
+                    self.html_row(
+                        "",
+                        &[String::new(), escape_html(labels.total)],
+                        &[user.total.clone(), String::new()],
+                        &user.delta
+                    )
+                );
+            }
+        }
+        let ranking_max = self
+            .summary
+            .projects
+            .first()
+            .map(|p| p.duration)
+            .unwrap_or_default();
+        html += &format!(
+            "<h2>{}</h2>\n<table>\n{}<tbody>\n",
+            escape_html(labels.ranking_title),
+            self.html_header(&["#", "", labels.project, labels.hours, labels.share])
+        );
+        for project in &self.summary.projects {
+            html += &self.html_row(
+                "",
+                &[
+                    project.rank.to_string(),
+                    self.svg_bar(&project.name, &project.duration, &ranking_max),
+                    escape_html(&project.name),
+                ],
+                &[project.time.clone(), project.share.clone()],
+                &project.delta,
+            );
+        }
+        html += &format!(
+            "</tbody>\n<tfoot>\n{}</tfoot>\n</table>\n",
+            self.html_row(
+                "",
+                &[String::new(), String::new(), escape_html(labels.total)],
+                &[self.summary.total.clone(), String::new()],
+                &self.summary.delta
+            )
+        );
+        html += &format!("<h2>{}</h2>\n<table>\n", escape_html(labels.detailed_title));
+        for (i, row) in self.detailed.iter().enumerate() {
+            let tag = if i == 0 { "th" } else { "td" };
+            html += "<tr>";
+            for cell in row {
+                html += &format!("<{tag}>{}</{tag}>", escape_html(cell), tag = tag);
+            }
+            html += "</tr>\n";
+        }
+        html + "</table>\n</body>\n</html>\n"
+    }
+
+    /// Returns the header row of escaped `columns`, adding the change column if compared
+    fn html_header(&self, columns: &[&str]) -> String {
+        let mut columns = columns.to_vec();
+        if self.summary.comparison {
+            columns.push(self.summary.labels.change);
+        }
+        format!(
+            "<thead><tr>{}</tr></thead>\n",
+            columns
+                .iter()
+                .map(|c| format!("<th>{}</th>", escape_html(c)))
+                .collect::<String>()
+        )
+    }
+
+    /// Returns a row of escaped `texts` and hours or percentages in `numbers`, adding `delta` if compared
+    fn html_row(
+        &self,
+        attributes: &str,
+        texts: &[String],
+        numbers: &[String],
+        delta: &str,
+    ) -> String {
+        let mut cells: Vec<String> = texts.iter().map(|t| format!("<td>{}</td>", t)).collect();
+        cells.extend(
+            numbers
+                .iter()
+                .map(|n| format!("<td class=\"number\">{}</td>", escape_html(n))),
+        );
+        if self.summary.comparison {
+            cells.push(format!("<td>{}</td>", escape_html(delta)));
+        }
+        format!("<tr{}>{}</tr>\n", attributes, cells.concat())
+    }
+
+    /// Draws the bar of `dur` relative to `max` in the colour derived from the project name
+    ///
+    /// Toggl project colours are not fetched for documents, so the bars may differ in colour from the PNG charts.
+    fn svg_bar(&self, project: &str, dur: &Duration, max: &Duration) -> String {
+        let width = (dur.value * Self::BAR_WIDTH)
+            .checked_div(max.value)
+            .unwrap_or(0);
+        format!(
+            "<svg width=\"{max}\" height=\"12\"><rect width=\"{width}\" height=\"12\" fill=\"{color}\"/></svg>",
+            max = Self::BAR_WIDTH,
+            width = width,
+            color = Rgb::from_name(project).to_hex(),
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let labels = &self.summary.labels;
+        let mut markdown = format!("# {}\n", escape_markdown(&self.title()));
+        for workspace in &self.summary.workspaces {
+            if !workspace.name.is_empty() {
+                markdown += &format!("\n## {}\n", escape_markdown(&workspace.name));
+            }
+            for user in &workspace.users {
+                markdown += &format!(
+                    "\n### {}\n\n{}",
+                    escape_markdown(&user.name),
+                    self.markdown_header(&[labels.project, labels.hours, labels.share], 1)
+                );
+                for project in &user.projects {
+                    markdown += &self.markdown_row(
+                        &[
+                            escape_markdown(&self.project_name(project)),
+                            escape_markdown(&project.time),
+                            escape_markdown(&project.share),
+                        ],
+                        &project.delta,
+                    );
+                    for description in &project.descriptions {
+                        markdown += &self.markdown_row(
+                            &[
+                                format!("↳ {}", escape_markdown(&description.name)),
+                                escape_markdown(&description.time),
+                                String::new(),
+                            ],
+                            "",
+                        );
+                    }
+                }
+                markdown += &self.markdown_row(
+                    &[bold(labels.total), bold(&user.total), String::new()],
+                    &user.delta,
+                );
+            }
+        }
+        markdown += &format!(
+            "\n## {}\n\n{}",
+            escape_markdown(labels.ranking_title),
+            self.markdown_header(&["#", labels.project, labels.hours, labels.share], 2)
+        );
+        for project in &self.summary.projects {
+            markdown += &self.markdown_row(
+                &[
+                    project.rank.to_string(),
+                    escape_markdown(&project.name),
+                    escape_markdown(&project.time),
+                    escape_markdown(&project.share),
+                ],
+                &project.delta,
+            );
+        }
+        markdown += &self.markdown_row(
+            &[
+                String::new(),
+                bold(labels.total),
+                bold(&self.summary.total),
+                String::new(),
+            ],
+            &self.summary.delta,
+        );
+        markdown += &format!("\n## {}\n\n", escape_markdown(labels.detailed_title));
+        for (i, row) in self.detailed.iter().enumerate() {
+            let cells: Vec<String> = row.iter().map(|c| escape_markdown(c)).collect();
+            markdown += &Self::markdown_cells(&cells);
+            if i == 0 {
+                markdown += &Self::markdown_cells(&vec!["---".to_string(); row.len()]);
+            }
+        }
+        markdown
+    }
+
+    /// Returns the header and the delimiter row, where columns from `texts` are aligned to the left
+    fn markdown_header(&self, columns: &[&str], texts: usize) -> String {
+        let mut columns: Vec<String> = columns.iter().map(|c| escape_markdown(c)).collect();
+        if self.summary.comparison {
+            columns.push(escape_markdown(self.summary.labels.change));
+        }
+        let delimiters: Vec<String> = (0..columns.len())
+            .map(|i| if i < texts { "---" } else { "---:" }.to_string())
+            .collect();
+        Self::markdown_cells(&columns) + &Self::markdown_cells(&delimiters)
+    }
+
+    /// Returns a row of Markdown `cells`, adding `delta` if compared
+    fn markdown_row(&self, cells: &[String], delta: &str) -> String {
+        let mut cells = cells.to_vec();
+        if self.summary.comparison {
+            cells.push(escape_markdown(delta));
+        }
+        Self::markdown_cells(&cells)
+    }
+
+    fn markdown_cells(cells: &[String]) -> String {
+        format!("| {} |\n", cells.join(" | "))
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes characters which start Markdown syntax or end cells of tables
+fn escape_markdown(s: &str) -> String {
+    s.chars().fold(String::new(), |mut acc, c| {
+        if "\\`*_[]<>#|".contains(c) {
+            acc.push('\\');
+        }
+        acc.push(c);
+        acc
+    })
+}
+
+/// Returns `s` escaped and in bold
+fn bold(s: &str) -> String {
+    format!("**{}**", escape_markdown(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{MessageCreator, SummaryAnnotations};
+    use crate::values::{Project, ProjectRecords, User};
+    use chrono::prelude::*;
+
+    fn summary() -> SummaryContext {
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![
+                    (Project::new(Some("A|B")), Duration::new(5_400_000)),
+                    (Project::new(Some("<C>")), Duration::new(1_800_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        MessageCreator::default().get_summary_context(
+            &project_times_by_user,
+            &SummaryAnnotations::default(),
+            &NaiveDate::from_ymd(2020, 12, 1),
+            &NaiveDate::from_ymd(2020, 12, 7),
+        )
+    }
+
+    #[test]
+    fn to_markdown_must_render_tables_escaping_names() {
+        let summary = summary();
+        let detailed = vec![
+            vec!["Project".to_string(), "2020-12-01".to_string()],
+            vec!["A|B".to_string(), "1.5".to_string()],
+            vec!["*x*_y_".to_string(), "0.5".to_string()],
+        ];
+        let document = Document {
+            summary: &summary,
+            detailed: &detailed,
+            locale: Locale::En,
+        };
+
+        let expected = format!(
+            "{}{}{}{}",
+            "# Toggl summary report 2020/12/01-2020/12/07\n\n### Alice\n\n",
+            "| Project | Hours | Share |\n| --- | ---: | ---: |\n| A\\|B | 1.5 | 75% |\n| \\<C\\> | 0.5 | 25% |\n| **Total** | **2** |  |\n",
+            "\n## Projects by hours\n\n| \\# | Project | Hours | Share |\n| --- | --- | ---: | ---: |\n| 1 | A\\|B | 1.5 | 75% |\n| 2 | \\<C\\> | 0.5 | 25% |\n|  | **Total** | **2** |  |\n",
+            "\n## Hours per day\n\n| Project | 2020-12-01 |\n| --- | --- |\n| A\\|B | 1.5 |\n| \\*x\\*\\_y\\_ | 0.5 |\n",
+        );
+        assert_eq!(document.to_markdown(), expected);
+    }
+
+    #[test]
+    fn to_html_must_escape_names_and_draw_bars_relative_to_the_longest() {
+        let summary = summary();
+        let document = Document {
+            summary: &summary,
+            detailed: &[],
+            locale: Locale::En,
+        };
+
+        let html = document.to_html();
+        assert!(
+            html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<td>&lt;C&gt;</td>"), "{}", html);
+        assert!(!html.contains("<C>"), "{}", html);
+        assert!(html.contains("<rect width=\"200\""), "{}", html);
+        assert!(html.contains("<rect width=\"66\""), "{}", html);
+    }
+
+    #[test]
+    fn to_html_must_escape_labels_in_headers() {
+        let mut summary = summary();
+        summary.labels.project = "R&D <project>";
+        let document = Document {
+            summary: &summary,
+            detailed: &[],
+            locale: Locale::En,
+        };
+
+        let html = document.to_html();
+        assert!(
+            html.contains("<th>R&amp;D &lt;project&gt;</th>"),
+            "{}",
+            html
+        );
+        assert!(!html.contains("<project>"), "{}", html);
+    }
+}
@@ -0,0 +1,371 @@
+use crate::bucket::{Bucket, Bucketing, Granularity};
+use crate::calendar::Calendar;
+use crate::toggl::RecordKey;
+use crate::values::{Duration, Period, User};
+use chrono::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Hours expected on each day of week
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct WeeklyHours {
+    #[serde(default)]
+    pub mon: f64,
+    #[serde(default)]
+    pub tue: f64,
+    #[serde(default)]
+    pub wed: f64,
+    #[serde(default)]
+    pub thu: f64,
+    #[serde(default)]
+    pub fri: f64,
+    #[serde(default)]
+    pub sat: f64,
+    #[serde(default)]
+    pub sun: f64,
+}
+
+impl Default for WeeklyHours {
+    /// Returns 8 hours on weekdays
+    fn default() -> Self {
+        WeeklyHours {
+            mon: 8.0,
+            tue: 8.0,
+            wed: 8.0,
+            thu: 8.0,
+            fri: 8.0,
+            sat: 0.0,
+            sun: 0.0,
+        }
+    }
+}
+
+impl WeeklyHours {
+    pub fn on(&self, weekday: Weekday) -> f64 {
+        match weekday {
+            Weekday::Mon => self.mon,
+            Weekday::Tue => self.tue,
+            Weekday::Wed => self.wed,
+            Weekday::Thu => self.thu,
+            Weekday::Fri => self.fri,
+            Weekday::Sat => self.sat,
+            Weekday::Sun => self.sun,
+        }
+    }
+}
+
+/// Settings of a user, which override the defaults
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct UserExpectation {
+    pub hours: Option<WeeklyHours>,
+    pub weekly_max: Option<f64>,
+    /// Slack member ID mentioned in alerts, e.g. "U012AB3CD"
+    pub slack_id: Option<String>,
+}
+
+/// Hours which users are expected to log, read from a JSON file like
+///
+/// ```json
+/// {
+///   "default": {"mon": 8, "tue": 8, "wed": 8, "thu": 8, "fri": 8},
+///   "weekly_max": 40,
+///   "min_ratio": 0.75,
+///   "holidays": ["2020-12-31"],
+///   "users": {"Alice": {"hours": {"mon": 4, "wed": 4}, "slack_id": "U012AB3CD"}}
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ExpectedHours {
+    /// Hours of users without their own, which are 8 hours on weekdays by default
+    #[serde(default)]
+    pub default: WeeklyHours,
+    /// Maximum hours of a week, which is not checked if None
+    pub weekly_max: Option<f64>,
+    /// Days whose hours are below this ratio of the expected hours are flagged
+    #[serde(default = "ExpectedHours::default_min_ratio")]
+    pub min_ratio: f64,
+    /// Days when no hours are expected
+    #[serde(default)]
+    pub holidays: BTreeSet<NaiveDate>,
+    /// Settings by user name
+    #[serde(default)]
+    pub users: BTreeMap<String, UserExpectation>,
+}
+
+impl Default for ExpectedHours {
+    fn default() -> Self {
+        ExpectedHours {
+            default: WeeklyHours::default(),
+            weekly_max: None,
+            min_ratio: Self::default_min_ratio(),
+            holidays: BTreeSet::new(),
+            users: BTreeMap::new(),
+        }
+    }
+}
+
+/// Day or week flagged by the expected hours
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LoggingAlert {
+    /// No time entries on a day when hours are expected
+    Missing { date: NaiveDate, expected: Duration },
+    /// Fewer hours than `ExpectedHours::min_ratio` of the expected hours
+    TooFew {
+        date: NaiveDate,
+        logged: Duration,
+        expected: Duration,
+    },
+    /// More hours than the weekly maximum in a week, which is clipped to the report period
+    OverWeeklyMax {
+        week: Bucket,
+        logged: Duration,
+        max: Duration,
+    },
+}
+
+/// Alerts of a user in order of date
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserAlerts {
+    pub user: User,
+    pub slack_id: Option<String>,
+    pub alerts: Vec<LoggingAlert>,
+}
+
+impl ExpectedHours {
+    fn default_min_ratio() -> f64 {
+        0.75
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn hours_to_duration(hours: f64) -> Duration {
+        Duration::new((hours * 3_600_000.0).round() as u64)
+    }
+
+    /// Returns the expected hours of `user` on `date`, which are 0 on holidays
+    pub fn expected_on(&self, user: &User, date: &NaiveDate) -> Duration {
+        if self.holidays.contains(date) {
+            return Duration::new(0);
+        }
+        let hours = self
+            .users
+            .get(&user.value)
+            .and_then(|u| u.hours)
+            .unwrap_or(self.default);
+        Self::hours_to_duration(hours.on(date.weekday()))
+    }
+
+    /// Flags days of too few hours and weeks over the maximum of each user
+    ///
+    /// Users are those who logged time in the period and those configured in `users`.
+    /// Days from `today` are not flagged as missing because they may be still logged,
+    /// and neither are the days off of `calendar`.
+    pub fn check(
+        &self,
+        records: &[(RecordKey, Duration)],
+        period: &Period,
+        today: &NaiveDate,
+        week_start: Weekday,
+        calendar: Option<&Calendar>,
+    ) -> Vec<UserAlerts> {
+        let mut logged: HashMap<(&User, NaiveDate), Duration> = HashMap::new();
+        for (key, dur) in records {
+            let d = logged.entry((&key.user, key.date)).or_default();
+            *d = *d + *dur;
+        }
+        let configured: Vec<User> = self.users.keys().map(User::new).collect();
+        let users: BTreeSet<&User> = records
+            .iter()
+            .map(|(key, _)| &key.user)
+            .chain(configured.iter())
+            .collect();
+        let dates: Vec<NaiveDate> = period
+            .begin
+            .iter_days()
+            .take_while(|date| date <= &period.end)
+            .collect();
+        let weeks = Bucketing {
+            granularity: Granularity::Week,
+            week_start,
+        };
+
+        users
+            .into_iter()
+            .map(|user| {
+                let logged_on =
+                    |date: &NaiveDate| logged.get(&(user, *date)).copied().unwrap_or_default();
+                let mut alerts: Vec<LoggingAlert> = dates
+                    .iter()
+                    .filter(|date| *date < today)
+                    .filter(|date| !calendar.map(|c| c.is_day_off(date)).unwrap_or(false))
+                    .filter_map(|date| {
+                        let expected = self.expected_on(user, date);
+                        let logged = logged_on(date);
+                        if expected.value == 0 {
+                            None
+                        } else if logged.value == 0 {
+                            Some(LoggingAlert::Missing {
+                                date: *date,
+                                expected,
+                            })
+                        } else if (logged.value as f64) < expected.value as f64 * self.min_ratio {
+                            Some(LoggingAlert::TooFew {
+                                date: *date,
+                                logged,
+                                expected,
+                            })
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                let weekly_max = self
+                    .users
+                    .get(&user.value)
+                    .and_then(|u| u.weekly_max)
+                    .or(self.weekly_max);
+                if let Some(max) = weekly_max.map(Self::hours_to_duration) {
+                    let mut by_week: BTreeMap<NaiveDate, Vec<NaiveDate>> = BTreeMap::new();
+                    for date in &dates {
+                        by_week
+                            .entry(weeks.first_day(date, &period.begin))
+                            .or_default()
+                            .push(*date);
+                    }
+                    for (first, week_dates) in by_week {
+                        let logged = week_dates
+                            .iter()
+                            .fold(Duration::new(0), |acc, date| acc + logged_on(date));
+                        if logged.value > max.value {
+                            alerts.push(LoggingAlert::OverWeeklyMax {
+                                week: Bucket {
+                                    first,
+                                    last: *week_dates.last().unwrap_or(&first),
+                                },
+                                logged,
+                                max,
+                            });
+                        }
+                    }
+                }
+                UserAlerts {
+                    user: user.clone(),
+                    slack_id: self.users.get(&user.value).and_then(|u| u.slack_id.clone()),
+                    alerts,
+                }
+            })
+            .filter(|user_alerts| !user_alerts.alerts.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::values::Project;
+
+    fn record(user: &str, day: u32, hours: f64) -> (RecordKey, Duration) {
+        (
+            RecordKey {
+                user: User::new(user),
+                project: Project::new(Some("ProjectA")),
+                date: NaiveDate::from_ymd(2020, 12, day),
+            },
+            ExpectedHours::hours_to_duration(hours),
+        )
+    }
+
+    #[test]
+    fn from_file_json_must_override_defaults_per_user() {
+        let json = r#"{
+            "holidays": ["2020-12-08"],
+            "users": {"Bob": {"hours": {"mon": 4}, "slack_id": "U012AB3CD"}}
+        }"#;
+        let expected: ExpectedHours = serde_json::from_str(json).unwrap();
+        let monday = NaiveDate::from_ymd(2020, 12, 7);
+
+        assert_eq!(
+            expected.expected_on(&User::new("Alice"), &monday),
+            Duration::new(8 * 3_600_000)
+        );
+        assert_eq!(
+            expected.expected_on(&User::new("Bob"), &monday),
+            Duration::new(4 * 3_600_000)
+        );
+        assert_eq!(
+            expected.expected_on(&User::new("Alice"), &monday.succ()),
+            Duration::new(0)
+        );
+        assert_eq!(expected.min_ratio, 0.75);
+    }
+
+    #[test]
+    fn check_must_flag_missing_and_short_days_and_long_weeks() {
+        let expected = ExpectedHours {
+            weekly_max: Some(20.0),
+            holidays: [NaiveDate::from_ymd(2020, 12, 10)]
+                .iter()
+                .cloned()
+                .collect(),
+            users: [("Bob".to_string(), UserExpectation::default())]
+                .iter()
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        let records = vec![
+            record("Alice", 7, 8.0),
+            record("Alice", 8, 5.0),
+            record("Alice", 9, 8.0),
+            record("Alice", 11, 8.0),
+            record("Alice", 12, 2.0),
+        ];
+        // Monday to Saturday, where Friday is today and Thursday is a holiday
+        let period = Period::new(
+            NaiveDate::from_ymd(2020, 12, 7),
+            NaiveDate::from_ymd(2020, 12, 12),
+        );
+        let today = NaiveDate::from_ymd(2020, 12, 11);
+
+        let actual = expected.check(&records, &period, &today, Weekday::Mon, None);
+        let hours = ExpectedHours::hours_to_duration;
+        let expected = vec![
+            UserAlerts {
+                user: User::new("Alice"),
+                slack_id: None,
+                alerts: vec![
+                    LoggingAlert::TooFew {
+                        date: NaiveDate::from_ymd(2020, 12, 8),
+                        logged: hours(5.0),
+                        expected: hours(8.0),
+                    },
+                    LoggingAlert::OverWeeklyMax {
+                        week: Bucket {
+                            first: NaiveDate::from_ymd(2020, 12, 7),
+                            last: NaiveDate::from_ymd(2020, 12, 12),
+                        },
+                        logged: hours(31.0),
+                        max: hours(20.0),
+                    },
+                ],
+            },
+            UserAlerts {
+                user: User::new("Bob"),
+                slack_id: None,
+                alerts: (7..=9)
+                    .map(|day| LoggingAlert::Missing {
+                        date: NaiveDate::from_ymd(2020, 12, day),
+                        expected: hours(8.0),
+                    })
+                    .collect(),
+            },
+        ];
+        assert_eq!(actual, expected);
+    }
+}
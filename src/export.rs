@@ -0,0 +1,363 @@
+use crate::toggl::{RecordKey, TogglDetail};
+use crate::values::{Duration, Period, Project, User, Workspace};
+use chrono::prelude::*;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// Aggregated report written by `--format json` for other tools
+///
+/// The layout is described by `schemas/report.schema.json`, whose version is incremented on incompatible changes.
+/// Durations are in milliseconds, and IDs are null if Toggl does not give them, e.g. in CSV exports.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JsonReport {
+    pub schema_version: u32,
+    pub period: JsonPeriod,
+    /// Total time of everyone
+    pub total: u64,
+    /// Time of each user and project in each workspace
+    pub workspaces: Vec<WorkspaceTotal>,
+    /// Time of each project summed up over the users, sorted by time descendingly
+    pub projects: Vec<RankedProject>,
+    pub days: DailyTable,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JsonPeriod {
+    /// First day in `YYYY-MM-DD`
+    pub begin: String,
+    /// Last day in `YYYY-MM-DD`
+    pub end: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WorkspaceTotal {
+    pub workspace: Workspace,
+    pub workspace_id: Option<u64>,
+    pub total: u64,
+    pub users: Vec<UserTotal>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct UserTotal {
+    pub user: String,
+    pub user_id: Option<u64>,
+    pub total: u64,
+    pub projects: Vec<ProjectTotal>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ProjectTotal {
+    /// Serialized as the name of the project, or null for time entries without a project
+    pub project: Project,
+    pub project_id: Option<u64>,
+    pub duration: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RankedProject {
+    pub workspace: Workspace,
+    pub project: Project,
+    pub project_id: Option<u64>,
+    pub duration: u64,
+}
+
+/// Time per day of each user and project, regardless of the layout of the CSV
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DailyTable {
+    /// Every day of the period in `YYYY-MM-DD`
+    pub dates: Vec<String>,
+    pub rows: Vec<DailyRow>,
+    /// Time of everyone on each day of `dates`
+    pub totals: Vec<u64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DailyRow {
+    pub workspace: Workspace,
+    pub user: String,
+    pub user_id: Option<u64>,
+    pub project: Project,
+    pub project_id: Option<u64>,
+    /// Time on each day of `dates`
+    pub durations: Vec<u64>,
+    pub total: u64,
+}
+
+/// Time entries of a workspace to export
+pub struct WorkspaceRecords<'a> {
+    pub workspace: &'a Workspace,
+    /// ID of the workspace, which is None for saved reports
+    pub workspace_id: Option<u64>,
+    /// Time entries to take the IDs of users and projects from
+    pub details: &'a [TogglDetail],
+    /// Time per day of each user and project, which every total is summed up from
+    pub records: Vec<(RecordKey, Duration)>,
+}
+
+/// IDs of the users and projects of a workspace
+struct Ids<'a> {
+    users: HashMap<&'a User, u64>,
+    projects: HashMap<&'a Project, u64>,
+}
+
+impl<'a> Ids<'a> {
+    fn from_details(details: &'a [TogglDetail]) -> Self {
+        let mut ids = Ids {
+            users: HashMap::new(),
+            projects: HashMap::new(),
+        };
+        for detail in details {
+            if let Some(uid) = detail.uid {
+                ids.users.entry(&detail.user).or_insert(uid);
+            }
+            if let Some(pid) = detail.pid {
+                ids.projects.entry(&detail.project).or_insert(pid);
+            }
+        }
+        ids
+    }
+}
+
+impl JsonReport {
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Builds the report from the records by day of each workspace
+    ///
+    /// Every total is summed up from the records, so that they agree with `days`
+    /// even if the summary of the messages comes from Summary API.
+    pub fn new(period: &Period, workspaces: &[WorkspaceRecords]) -> Self {
+        let dates: Vec<NaiveDate> = period
+            .begin
+            .iter_days()
+            .take_while(|date| date <= &period.end)
+            .collect();
+        let mut totals = vec![0; dates.len()];
+        let mut workspace_totals = vec![];
+        let mut projects = vec![];
+        let mut rows = vec![];
+        for w in workspaces {
+            let ids = Ids::from_details(w.details);
+            let mut durations_by_key: BTreeMap<(&User, &Project), Vec<u64>> = BTreeMap::new();
+            for (key, dur) in w
+                .records
+                .iter()
+                .filter(|(key, _)| period.contains(&key.date))
+            {
+                let i = (key.date - period.begin).num_days() as usize;
+                let durations = durations_by_key
+                    .entry((&key.user, &key.project))
+                    .or_insert_with(|| vec![0; dates.len()]);
+                durations[i] += dur.value;
+                totals[i] += dur.value;
+            }
+
+            let mut users: Vec<UserTotal> = vec![];
+            let mut durations_by_project: BTreeMap<&Project, u64> = BTreeMap::new();
+            for ((user, project), durations) in durations_by_key {
+                let total: u64 = durations.iter().sum();
+                if users.last().map_or(true, |u| u.user != user.value) {
+                    users.push(UserTotal {
+                        user: user.value.clone(),
+                        user_id: ids.users.get(user).copied(),
+                        total: 0,
+                        projects: vec![],
+                    });
+                }
+                if let Some(u) = users.last_mut() {
+                    u.total += total;
+                    u.projects.push(ProjectTotal {
+                        project: project.clone(),
+                        project_id: ids.projects.get(project).copied(),
+                        duration: total,
+                    });
+                }
+                *durations_by_project.entry(project).or_default() += total;
+                rows.push(DailyRow {
+                    workspace: w.workspace.clone(),
+                    user: user.value.clone(),
+                    user_id: ids.users.get(user).copied(),
+                    project: project.clone(),
+                    project_id: ids.projects.get(project).copied(),
+                    durations,
+                    total,
+                });
+            }
+            projects.extend(durations_by_project.into_iter().map(|(project, duration)| {
+                RankedProject {
+                    workspace: w.workspace.clone(),
+                    project: project.clone(),
+                    project_id: ids.projects.get(project).copied(),
+                    duration,
+                }
+            }));
+            workspace_totals.push(WorkspaceTotal {
+                workspace: w.workspace.clone(),
+                workspace_id: w.workspace_id,
+                total: users.iter().map(|u| u.total).sum(),
+                users,
+            });
+        }
+        projects.sort_by_key(|p| Reverse(p.duration));
+
+        JsonReport {
+            schema_version: Self::SCHEMA_VERSION,
+            period: JsonPeriod {
+                begin: period.begin.format("%Y-%m-%d").to_string(),
+                end: period.end.format("%Y-%m-%d").to_string(),
+            },
+            total: totals.iter().sum(),
+            workspaces: workspace_totals,
+            projects,
+            days: DailyTable {
+                dates: dates
+                    .iter()
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .collect(),
+                rows,
+                totals,
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn detail(user: &str, uid: u64, project: Option<&str>, pid: Option<u64>) -> TogglDetail {
+        TogglDetail {
+            description: String::new(),
+            task: None,
+            start: FixedOffset::east(0).ymd(2020, 12, 1).and_hms(9, 0, 0),
+            dur: Duration::new(0),
+            user: User::new(user),
+            project: Project::new(project),
+            uid: Some(uid),
+            pid,
+            client: None,
+            is_billable: false,
+            billable: None,
+            cur: None,
+            running: false,
+        }
+    }
+
+    fn record(user: &str, project: Option<&str>, day: u32, dur: u64) -> (RecordKey, Duration) {
+        (
+            RecordKey {
+                user: User::new(user),
+                project: Project::new(project),
+                date: NaiveDate::from_ymd(2020, 12, day),
+            },
+            Duration::new(dur),
+        )
+    }
+
+    #[test]
+    fn to_json_must_sum_up_every_total_of_workspaces_from_the_records() {
+        let period = Period::new(
+            NaiveDate::from_ymd(2020, 12, 1),
+            NaiveDate::from_ymd(2020, 12, 2),
+        );
+        let details = vec![
+            detail("Alice", 1, Some("ProjectA"), Some(10)),
+            detail("Bob", 2, None, None),
+        ];
+        let other_details = vec![detail("Alice", 3, Some("ProjectA"), Some(30))];
+        let main = Workspace::new("Main");
+        let other = Workspace::new("Other");
+        let workspaces = vec![
+            WorkspaceRecords {
+                workspace: &main,
+                workspace_id: Some(100),
+                details: &details,
+                records: vec![
+                    record("Alice", Some("ProjectA"), 1, 100),
+                    record("Alice", Some("ProjectA"), 2, 200),
+                    record("Bob", None, 2, 100),
+                    record("Bob", Some("ProjectA"), 1, 50),
+                    record("Bob", Some("ProjectA"), 3, 999),
+                ],
+            },
+            WorkspaceRecords {
+                workspace: &other,
+                workspace_id: Some(200),
+                details: &other_details,
+                records: vec![record("Alice", Some("ProjectA"), 2, 400)],
+            },
+        ];
+
+        let report = JsonReport::new(&period, &workspaces);
+        let actual: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        let expected = json!({
+            "schema_version": 1,
+            "period": {"begin": "2020-12-01", "end": "2020-12-02"},
+            "total": 850,
+            "workspaces": [
+                {"workspace": "Main", "workspace_id": 100, "total": 450, "users": [
+                    {"user": "Alice", "user_id": 1, "total": 300, "projects": [
+                        {"project": "ProjectA", "project_id": 10, "duration": 300}
+                    ]},
+                    {"user": "Bob", "user_id": 2, "total": 150, "projects": [
+                        {"project": null, "project_id": null, "duration": 100},
+                        {"project": "ProjectA", "project_id": 10, "duration": 50}
+                    ]}
+                ]},
+                {"workspace": "Other", "workspace_id": 200, "total": 400, "users": [
+                    {"user": "Alice", "user_id": 3, "total": 400, "projects": [
+                        {"project": "ProjectA", "project_id": 30, "duration": 400}
+                    ]}
+                ]}
+            ],
+            "projects": [
+                {"workspace": "Other", "project": "ProjectA", "project_id": 30, "duration": 400},
+                {"workspace": "Main", "project": "ProjectA", "project_id": 10, "duration": 350},
+                {"workspace": "Main", "project": null, "project_id": null, "duration": 100}
+            ],
+            "days": {
+                "dates": ["2020-12-01", "2020-12-02"],
+                "rows": [
+                    {"workspace": "Main", "user": "Alice", "user_id": 1, "project": "ProjectA", "project_id": 10, "durations": [100, 200], "total": 300},
+                    {"workspace": "Main", "user": "Bob", "user_id": 2, "project": null, "project_id": null, "durations": [0, 100], "total": 100},
+                    {"workspace": "Main", "user": "Bob", "user_id": 2, "project": "ProjectA", "project_id": 10, "durations": [50, 0], "total": 50},
+                    {"workspace": "Other", "user": "Alice", "user_id": 3, "project": "ProjectA", "project_id": 30, "durations": [0, 400], "total": 400}
+                ],
+                "totals": [150, 700]
+            }
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn schema_must_describe_the_version_and_the_properties() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schemas/report.schema.json")).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            json!(JsonReport::SCHEMA_VERSION)
+        );
+        let report = JsonReport::new(
+            &Period::new(
+                NaiveDate::from_ymd(2020, 12, 1),
+                NaiveDate::from_ymd(2020, 12, 1),
+            ),
+            &[],
+        );
+        let keys: Vec<String> = serde_json::to_value(&report)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let mut required: Vec<String> = serde_json::from_value(schema["required"].clone()).unwrap();
+        required.sort();
+        assert_eq!(keys, required);
+    }
+}
@@ -0,0 +1,461 @@
+use crate::catalog::Catalog;
+use crate::toggl::{TogglClient, TogglDetail};
+use crate::values::{Duration, Project, ProjectRecords, User};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Matches a user, project or client by its ID or by its name
+///
+/// Names may contain glob wildcards: `*` matches any characters and `?` matches one character.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Matcher {
+    Id(u64),
+    Name(String),
+}
+
+impl Matcher {
+    /// Returns true if the given ID or name matches
+    ///
+    /// An ID matcher never matches when the ID is unknown.
+    pub fn matches(&self, id: Option<u64>, name: Option<&str>) -> bool {
+        match self {
+            Matcher::Id(expected) => id == Some(*expected),
+            Matcher::Name(pattern) => name
+                .map(|n| {
+                    let pattern: Vec<char> = pattern.chars().collect();
+                    let text: Vec<char> = n.chars().collect();
+                    Self::glob_match(&pattern, &text)
+                })
+                .unwrap_or(false),
+        }
+    }
+
+    fn glob_match(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('*', rest)) => (0..=text.len()).any(|i| Self::glob_match(rest, &text[i..])),
+            Some(('?', rest)) => !text.is_empty() && Self::glob_match(rest, &text[1..]),
+            Some((c, rest)) => text.first() == Some(c) && Self::glob_match(rest, &text[1..]),
+        }
+    }
+}
+
+impl FromStr for Matcher {
+    type Err = String;
+
+    /// Parses digits as an ID and anything else as a name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Empty filter is given".to_string());
+        }
+        Ok(s.parse::<u64>()
+            .map(Matcher::Id)
+            .unwrap_or_else(|_| Matcher::Name(s.to_string())))
+    }
+}
+
+/// Includes and excludes for one kind of entity
+///
+/// Everything is included if `include` is empty.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Rule {
+    pub include: Vec<Matcher>,
+    pub exclude: Vec<Matcher>,
+}
+
+impl Rule {
+    pub fn new(include: Vec<Matcher>, exclude: Vec<Matcher>) -> Self {
+        Rule { include, exclude }
+    }
+
+    pub fn allows(&self, id: Option<u64>, name: Option<&str>) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|m| m.matches(id, name)))
+            && !self.exclude.iter().any(|m| m.matches(id, name))
+    }
+
+    /// Returns the included IDs if the includes can be delegated to Toggl API
+    ///
+    /// Toggl API accepts IDs only, so names in the includes make it impossible.
+    fn pushdown_ids(&self) -> Option<String> {
+        if self.include.is_empty() {
+            return None;
+        }
+        self.include
+            .iter()
+            .map(|m| match m {
+                Matcher::Id(id) => Some(id.to_string()),
+                Matcher::Name(_) => None,
+            })
+            .collect::<Option<Vec<String>>>()
+            .map(|ids| ids.join(","))
+    }
+}
+
+/// Filters on users, projects and clients
+///
+/// The filters are passed to Toggl API as far as possible, and enforced locally as well.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReportFilter {
+    pub users: Rule,
+    pub projects: Rule,
+    pub clients: Rule,
+    /// Names of the clients by ID, which match the client names of the entries to the included IDs
+    pub client_names: BTreeMap<u64, String>,
+}
+
+impl ReportFilter {
+    pub fn new(users: Rule, projects: Rule, clients: Rule) -> Self {
+        ReportFilter {
+            users,
+            projects,
+            clients,
+            client_names: BTreeMap::new(),
+        }
+    }
+
+    /// Returns true if clients are included or excluded by ID, which needs the clients from Toggl API
+    ///
+    /// Client IDs are not included in the time entries, so they are matched by the names of the clients.
+    pub fn needs_client_names(&self) -> bool {
+        self.clients
+            .include
+            .iter()
+            .chain(&self.clients.exclude)
+            .any(|m| matches!(m, Matcher::Id(_)))
+    }
+
+    /// Sets the clients of the workspace to match clients by ID
+    pub fn with_clients(self, clients: &[TogglClient]) -> Self {
+        ReportFilter {
+            client_names: clients.iter().map(|c| (c.id, c.name.clone())).collect(),
+            ..self
+        }
+    }
+
+    /// Returns query parameters for Toggl Reports API
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        [
+            ("user_ids", &self.users),
+            ("project_ids", &self.projects),
+            ("client_ids", &self.clients),
+        ]
+        .iter()
+        .filter_map(|(key, rule)| rule.pushdown_ids().map(|ids| (*key, ids)))
+        .collect()
+    }
+
+    fn allows(
+        &self,
+        user: &User,
+        user_id: Option<u64>,
+        project: &Project,
+        project_id: Option<u64>,
+        client: Option<&str>,
+    ) -> bool {
+        // Toggl API uses 0 as the ID of "no project"
+        let project_id = match project.value {
+            Some(_) => project_id,
+            None => Some(0),
+        };
+        self.users.allows(user_id, Some(&user.value))
+            && self.projects.allows(project_id, Some(&project.to_string()))
+            && self.allows_client(client)
+    }
+
+    fn allows_client(&self, client: Option<&str>) -> bool {
+        let client_id = client.and_then(|name| {
+            self.client_names
+                .iter()
+                .find(|(_, n)| n.as_str() == name)
+                .map(|(id, _)| *id)
+        });
+        self.clients.allows(client_id, client)
+    }
+
+    fn allows_in_catalog(&self, user: &User, project: &Project, catalog: &Catalog) -> bool {
+        let project_info = catalog.project(project);
+        self.allows(
+            user,
+            catalog.user(user).id,
+            project,
+            project_info.id,
+            project_info.client.as_deref(),
+        )
+    }
+
+    /// Removes time entries which are not allowed by the filter
+    pub fn retain_details(&self, data: Vec<TogglDetail>) -> Vec<TogglDetail> {
+        data.into_iter()
+            .filter(|d| self.allows(&d.user, d.uid, &d.project, d.pid, d.client.as_deref()))
+            .collect()
+    }
+
+    /// Removes users and projects which are not allowed by the filter
+    ///
+    /// Users who have no project left are removed as well.
+    pub fn retain_project_records(
+        &self,
+        records: ProjectRecords,
+        catalog: &Catalog,
+    ) -> ProjectRecords {
+        let value = records
+            .value
+            .into_iter()
+            .map(|(u, project_times)| {
+                let project_times: Vec<(Project, Duration)> = project_times
+                    .into_iter()
+                    .filter(|(p, _)| self.allows_in_catalog(&u, p, catalog))
+                    .collect();
+                (u, project_times)
+            })
+            .filter(|(_, project_times)| !project_times.is_empty())
+            .collect();
+        ProjectRecords::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{ProjectInfo, UserInfo};
+    use chrono::prelude::*;
+
+    fn catalog() -> Catalog {
+        Catalog {
+            users: [
+                (User::new("Alice"), UserInfo { id: Some(1) }),
+                (User::new("Bob"), UserInfo { id: Some(2) }),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            projects: [
+                (
+                    Project::new(Some("ProjectA")),
+                    ProjectInfo {
+                        id: Some(10),
+                        client: Some("ClientX".to_string()),
+                    },
+                ),
+                (
+                    Project::new(Some("ProjectB")),
+                    ProjectInfo {
+                        id: Some(20),
+                        client: None,
+                    },
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        }
+    }
+
+    fn detail(user: &str, uid: u64, project: &str, client: Option<&str>) -> TogglDetail {
+        TogglDetail {
+            description: String::new(),
+            task: None,
+            start: FixedOffset::east(0).ymd(2020, 12, 1).and_hms(9, 0, 0),
+            dur: Duration::new(100),
+            user: User::new(user),
+            project: Project::new(Some(project)),
+            uid: Some(uid),
+            pid: None,
+            client: client.map(|c| c.to_string()),
+            is_billable: false,
+            billable: None,
+            cur: None,
+            running: false,
+        }
+    }
+
+    #[test]
+    fn matcher_must_parse_digits_as_id_and_others_as_name() {
+        assert_eq!("123".parse::<Matcher>(), Ok(Matcher::Id(123)));
+        assert_eq!(
+            "Project*".parse::<Matcher>(),
+            Ok(Matcher::Name("Project*".to_string()))
+        );
+        assert!("".parse::<Matcher>().is_err());
+    }
+
+    #[test]
+    fn matcher_must_match_glob_patterns() {
+        let m = Matcher::Name("Pro*ct?".to_string());
+
+        assert!(m.matches(None, Some("ProjectA")));
+        assert!(m.matches(None, Some("ProductB")));
+        assert!(!m.matches(None, Some("ProjectAB")));
+        assert!(!m.matches(None, None));
+    }
+
+    #[test]
+    fn query_must_contain_includes_which_consist_of_ids_only() {
+        let filter = ReportFilter::new(
+            Rule::new(vec![Matcher::Id(1), Matcher::Id(2)], vec![]),
+            Rule::new(
+                vec![Matcher::Id(10), Matcher::Name("B*".to_string())],
+                vec![],
+            ),
+            Rule::new(vec![], vec![Matcher::Name("ClientX".to_string())]),
+        );
+
+        let actual = filter.query();
+        let expected = vec![("user_ids", "1,2".to_string())];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn retain_project_records_must_remove_excluded_projects_and_empty_users() {
+        let filter = ReportFilter::new(
+            Rule::default(),
+            Rule::default(),
+            Rule::new(vec![Matcher::Name("Client*".to_string())], vec![]),
+        );
+        let alice = User::new("Alice");
+        let bob = User::new("Bob");
+        let project_a = Project::new(Some("ProjectA"));
+        let project_b = Project::new(Some("ProjectB"));
+        let dur = Duration::new(100);
+        let records = ProjectRecords::new(
+            [
+                (
+                    alice.clone(),
+                    vec![(project_a.clone(), dur), (project_b.clone(), dur)],
+                ),
+                (bob.clone(), vec![(project_b.clone(), dur)]),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+
+        let actual = filter.retain_project_records(records, &catalog());
+        let expected = ProjectRecords::new(
+            [(alice.clone(), vec![(project_a.clone(), dur)])]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn retain_details_must_apply_user_ids_and_project_names() {
+        let filter = ReportFilter::new(
+            Rule::new(vec![Matcher::Id(2)], vec![]),
+            Rule::new(vec![], vec![Matcher::Name("ProjectA".to_string())]),
+            Rule::default(),
+        );
+        let details = vec![
+            detail("Alice", 1, "ProjectB", None),
+            detail("Bob", 2, "ProjectA", None),
+            detail("Bob", 2, "ProjectB", None),
+        ];
+
+        let actual: Vec<_> = filter
+            .retain_details(details)
+            .into_iter()
+            .map(|d| (d.user, d.project))
+            .collect();
+        let expected = vec![(User::new("Bob"), Project::new(Some("ProjectB")))];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn retain_details_must_include_clients_by_id_with_their_names() {
+        let filter = ReportFilter::new(
+            Rule::default(),
+            Rule::default(),
+            Rule::new(vec![Matcher::Id(100)], vec![]),
+        )
+        .with_clients(&[
+            TogglClient {
+                id: 100,
+                name: "ClientX".to_string(),
+            },
+            TogglClient {
+                id: 200,
+                name: "ClientY".to_string(),
+            },
+        ]);
+        let details = vec![
+            detail("Alice", 1, "ProjectA", Some("ClientX")),
+            detail("Alice", 1, "ProjectB", Some("ClientY")),
+            detail("Alice", 1, "ProjectC", None),
+        ];
+
+        let actual: Vec<_> = filter
+            .retain_details(details)
+            .into_iter()
+            .map(|d| d.project)
+            .collect();
+        assert!(filter.needs_client_names());
+        assert_eq!(actual, vec![Project::new(Some("ProjectA"))])
+    }
+
+    #[test]
+    fn retain_details_must_mix_client_ids_and_names_and_exclude_clients_by_id() {
+        let filter = ReportFilter::new(
+            Rule::default(),
+            Rule::default(),
+            Rule::new(
+                vec![Matcher::Id(100), Matcher::Name("ClientY*".to_string())],
+                vec![Matcher::Id(201)],
+            ),
+        )
+        .with_clients(&[
+            TogglClient {
+                id: 100,
+                name: "ClientX".to_string(),
+            },
+            TogglClient {
+                id: 200,
+                name: "ClientY".to_string(),
+            },
+            TogglClient {
+                id: 201,
+                name: "ClientYY".to_string(),
+            },
+            TogglClient {
+                id: 300,
+                name: "ClientZ".to_string(),
+            },
+        ]);
+        let details = vec![
+            detail("Alice", 1, "ProjectA", Some("ClientX")),
+            detail("Alice", 1, "ProjectB", Some("ClientY")),
+            detail("Alice", 1, "ProjectC", Some("ClientYY")),
+            detail("Alice", 1, "ProjectD", Some("ClientZ")),
+        ];
+
+        let actual: Vec<_> = filter
+            .retain_details(details)
+            .into_iter()
+            .map(|d| d.project)
+            .collect();
+        assert!(filter.needs_client_names());
+        assert!(filter.query().is_empty());
+        assert_eq!(
+            actual,
+            vec![
+                Project::new(Some("ProjectA")),
+                Project::new(Some("ProjectB"))
+            ]
+        )
+    }
+
+    #[test]
+    fn project_id_0_must_match_entries_without_project() {
+        let filter = ReportFilter::new(
+            Rule::default(),
+            Rule::new(vec![Matcher::Id(0)], vec![]),
+            Rule::default(),
+        );
+        let user = User::new("Alice");
+
+        assert!(filter.allows(&user, None, &Project::new::<String>(None), None, None));
+        assert!(!filter.allows(&user, None, &Project::new(Some("A")), Some(10), None));
+    }
+}
@@ -1,4 +1,20 @@
+pub mod bucket;
+pub mod budget;
+pub mod cache;
+pub mod calendar;
+pub mod catalog;
+pub mod chart;
+pub mod document;
+pub mod expected;
+pub mod export;
+pub mod filter;
+pub mod listing;
+pub mod locale;
 pub mod message;
+pub mod plot;
+pub mod replay;
+pub mod rounding;
 pub mod slack;
+pub mod template;
 pub mod toggl;
 pub mod values;
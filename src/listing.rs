@@ -0,0 +1,171 @@
+use crate::toggl::{TogglClient, TogglProject, TogglTag, TogglWorkspace, TogglWorkspaceUser};
+use serde::Serialize;
+use unicode_width::UnicodeWidthStr;
+
+/// Item which can be listed by `<kind> list` subcommands
+pub trait Listable: Serialize {
+    fn header() -> Vec<&'static str>;
+    fn row(&self) -> Vec<String>;
+}
+
+impl Listable for TogglWorkspace {
+    fn header() -> Vec<&'static str> {
+        vec!["ID", "NAME"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.name.clone()]
+    }
+}
+
+impl Listable for TogglWorkspaceUser {
+    fn header() -> Vec<&'static str> {
+        vec!["ID", "NAME", "EMAIL"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.fullname.clone(),
+            self.email.clone(),
+        ]
+    }
+}
+
+impl Listable for TogglProject {
+    fn header() -> Vec<&'static str> {
+        vec!["ID", "NAME", "CLIENT_ID", "ACTIVE"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.cid.map(|cid| cid.to_string()).unwrap_or_default(),
+            self.active.to_string(),
+        ]
+    }
+}
+
+impl Listable for TogglClient {
+    fn header() -> Vec<&'static str> {
+        vec!["ID", "NAME"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.name.clone()]
+    }
+}
+
+impl Listable for TogglTag {
+    fn header() -> Vec<&'static str> {
+        vec!["ID", "NAME"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.name.clone()]
+    }
+}
+
+/// Item listed together with the workspace it belongs to
+#[derive(Serialize)]
+pub struct WorkspaceItem<T> {
+    pub workspace_id: String,
+    #[serde(flatten)]
+    pub item: T,
+}
+
+impl<T: Listable> Listable for WorkspaceItem<T> {
+    fn header() -> Vec<&'static str> {
+        [vec!["WORKSPACE_ID"], T::header()].concat()
+    }
+
+    fn row(&self) -> Vec<String> {
+        [vec![self.workspace_id.clone()], self.item.row()].concat()
+    }
+}
+
+/// Returns the items as JSON or as a table whose columns are aligned on monospaced display
+///
+/// CJK characters are counted as 2 columns.
+pub fn render<T: Listable>(items: &[T], json: bool) -> Result<String, serde_json::Error> {
+    if json {
+        return serde_json::to_string_pretty(items).map(|s| s + "\n");
+    }
+    let header: Vec<String> = T::header().iter().map(|h| h.to_string()).collect();
+    let rows: Vec<Vec<String>> = std::iter::once(header)
+        .chain(items.iter().map(|item| item.row()))
+        .collect();
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| rows.iter().map(|r| r[i].width()).max().unwrap_or(0))
+        .collect();
+    Ok(rows.iter().fold(String::new(), |acc, row| {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+            .collect::<Vec<String>>()
+            .join("  ");
+        acc + line.trim_end() + "\n"
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_must_align_columns() {
+        let items = vec![
+            TogglWorkspace {
+                id: 1234567,
+                name: "Company".to_string(),
+            },
+            TogglWorkspace {
+                id: 1,
+                name: "Community".to_string(),
+            },
+        ];
+
+        let actual = render(&items, false).unwrap();
+        let expected = "ID       NAME\n1234567  Company\n1        Community\n";
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn render_must_align_columns_of_cjk_names() {
+        let items = vec![
+            TogglWorkspaceUser {
+                id: 1,
+                email: "taro@example.com".to_string(),
+                fullname: "山田太郎".to_string(),
+            },
+            TogglWorkspaceUser {
+                id: 2,
+                email: "bob@example.com".to_string(),
+                fullname: "Bob".to_string(),
+            },
+        ];
+
+        let actual = render(&items, false).unwrap();
+        let expected =
+            "ID  NAME      EMAIL\n1   山田太郎  taro@example.com\n2   Bob       bob@example.com\n";
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn render_must_flatten_workspace_items_in_json() {
+        let items = vec![WorkspaceItem {
+            workspace_id: "100".to_string(),
+            item: TogglTag {
+                id: 1,
+                name: "urgent".to_string(),
+            },
+        }];
+
+        let actual: serde_json::Value =
+            serde_json::from_str(&render(&items, true).unwrap()).unwrap();
+        let expected = serde_json::json!([{"workspace_id": "100", "id": 1, "name": "urgent"}]);
+        assert_eq!(actual, expected)
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::prelude::*;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Translated words of messages and CSV
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct Labels {
+    pub summary_title: &'static str,
+    pub running: &'static str,
+    /// Title of the ranking of projects
+    pub ranking_title: &'static str,
+    pub total: &'static str,
+    pub project: &'static str,
+    pub user: &'static str,
+    pub workspace: &'static str,
+    pub billable: &'static str,
+    pub non_billable: &'static str,
+    pub billable_column: &'static str,
+    pub non_billable_column: &'static str,
+    /// Titles of the PNG charts
+    pub chart_hours_title: &'static str,
+    pub chart_share_title: &'static str,
+    /// Title of under and over-logging alerts
+    pub alerts_title: &'static str,
+    pub no_entries: &'static str,
+    pub expected: &'static str,
+    pub weekly_max: &'static str,
+    /// Days off marked in the CSV header
+    pub weekend: &'static str,
+    pub holiday: &'static str,
+    /// Title of the consumption of project budgets
+    pub budgets_title: &'static str,
+    pub monthly: &'static str,
+    pub remaining: &'static str,
+    pub over_budget: &'static str,
+    pub runs_out: &'static str,
+    /// Headers of the tables in HTML and Markdown
+    pub hours: &'static str,
+    pub share: &'static str,
+    pub change: &'static str,
+    pub detailed_title: &'static str,
+    /// strftime format of dates in messages
+    pub date_format: &'static str,
+}
+
+const EN: Labels = Labels {
+    summary_title: "Toggl summary report",
+    running: "running",
+    ranking_title: "Projects by hours",
+    total: "Total",
+    project: "Project",
+    user: "User",
+    workspace: "Workspace",
+    billable: "billable",
+    non_billable: "non-billable",
+    billable_column: "Billable",
+    non_billable_column: "NonBillable",
+    chart_hours_title: "Hours per day",
+    chart_share_title: "Share of projects",
+    alerts_title: "Time logging alerts",
+    no_entries: "no entries",
+    expected: "expected",
+    weekly_max: "weekly max",
+    weekend: "weekend",
+    holiday: "holiday",
+    budgets_title: "Project budgets",
+    monthly: "monthly",
+    remaining: "remaining",
+    over_budget: "over",
+    runs_out: "runs out",
+    hours: "Hours",
+    share: "Share",
+    change: "Change",
+    detailed_title: "Hours per day",
+    date_format: "%Y/%m/%d",
+};
+
+const JA: Labels = Labels {
+    summary_title: "Toggl 集計レポート",
+    running: "計測中",
+    ranking_title: "プロジェクト別ランキング",
+    total: "合計",
+    project: "プロジェクト",
+    user: "ユーザー",
+    workspace: "ワークスペース",
+    billable: "請求対象",
+    non_billable: "請求対象外",
+    billable_column: "請求対象",
+    non_billable_column: "請求対象外",
+    chart_hours_title: "日別作業時間",
+    chart_share_title: "プロジェクト別割合",
+    alerts_title: "工数入力アラート",
+    no_entries: "入力なし",
+    expected: "予定",
+    weekly_max: "週上限",
+    weekend: "週末",
+    holiday: "祝日",
+    budgets_title: "プロジェクト予算",
+    monthly: "月間",
+    remaining: "残り",
+    over_budget: "超過",
+    runs_out: "超過見込み",
+    hours: "時間",
+    share: "割合",
+    change: "増減",
+    detailed_title: "日別作業時間",
+    date_format: "%Y年%-m月%-d日",
+};
+
+/// Language of messages and CSV
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Locale {
+    En,
+    Ja,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::En
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parses a language tag, ignoring the region like `ja_JP.UTF-8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(|c| c == '-' || c == '_' || c == '.').next();
+        match language.map(|l| l.to_lowercase()).as_deref() {
+            Some("en") => Ok(Locale::En),
+            Some("ja") => Ok(Locale::Ja),
+            _ => Err(format!("Unknown locale: {}", s)),
+        }
+    }
+}
+
+impl Locale {
+    pub fn labels(&self) -> &'static Labels {
+        match self {
+            Locale::En => &EN,
+            Locale::Ja => &JA,
+        }
+    }
+
+    /// Language tag, e.g. "en" or "ja"
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ja => "ja",
+        }
+    }
+
+    /// e.g. "2020/12/01" or "2020年12月1日"
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        date.format(self.labels().date_format).to_string()
+    }
+
+    /// e.g. "Tue" or "火"
+    pub fn weekday(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            Locale::Ja => ["月", "火", "水", "木", "金", "土", "日"],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    /// Decimal separator of hours, which is `.` in both English and Japanese
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Locale::En | Locale::Ja => '.',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_must_ignore_regions_and_encodings() {
+        assert_eq!("en".parse::<Locale>(), Ok(Locale::En));
+        assert_eq!("ja_JP.UTF-8".parse::<Locale>(), Ok(Locale::Ja));
+        assert_eq!("ja-JP".parse::<Locale>(), Ok(Locale::Ja));
+        assert!("fr".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn format_date_must_follow_the_locale() {
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+
+        assert_eq!(Locale::En.format_date(&date), "2020/12/01");
+        assert_eq!(Locale::Ja.format_date(&date), "2020年12月1日");
+        assert_eq!(Locale::Ja.weekday(date.weekday()), "火");
+    }
+}
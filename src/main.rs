@@ -1,10 +1,253 @@
 extern crate clap;
+use toggl2slack::bucket::{Bucketing, Granularity};
+use toggl2slack::budget::Budgets;
+use toggl2slack::cache::ResponseCache;
+use toggl2slack::calendar::Calendar;
+use toggl2slack::chart::BarChart;
+use toggl2slack::document::{Document, OutputFormat};
+use toggl2slack::expected::ExpectedHours;
+use toggl2slack::export::{JsonReport, WorkspaceRecords};
+use toggl2slack::filter::{Matcher, ReportFilter, Rule};
+use toggl2slack::listing::{self, WorkspaceItem};
+use toggl2slack::locale::Locale;
 use toggl2slack::message;
+use toggl2slack::plot::{ChartRenderer, ProjectColors};
+use toggl2slack::replay::SavedReport;
+use toggl2slack::rounding::RoundingPolicy;
 use toggl2slack::slack;
+use toggl2slack::template::SummaryTemplate;
 use toggl2slack::toggl;
+use toggl2slack::values::{Period, Workspace};
 
 use chrono::prelude::*;
-use clap::{App, Arg};
+use chrono_tz::Tz;
+use clap::{App, AppSettings, Arg, ArgMatches};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+
+/// Builds the filter rule from `--include_<kind>` and `--exclude_<kind>`
+fn parse_rule(matches: &ArgMatches, kind: &str) -> Result<Rule, String> {
+    let parse = |key: String| {
+        matches
+            .values_of(key)
+            .map(|values| values.map(|v| v.parse::<Matcher>()).collect())
+            .unwrap_or_else(|| Ok(vec![]))
+    };
+    Ok(Rule::new(
+        parse(format!("include_{}", kind))?,
+        parse(format!("exclude_{}", kind))?,
+    ))
+}
+
+/// Builds the rounding policy of a renderer from `--rounding` and the given override
+fn rounding_policy(
+    matches: &ArgMatches,
+    key: &str,
+    locale: Locale,
+) -> Result<RoundingPolicy, String> {
+    let policy = RoundingPolicy {
+        decimal_separator: locale.decimal_separator(),
+        ..Default::default()
+    }
+    .with_spec(matches.value_of("rounding").unwrap_or(""))?;
+    policy.with_spec(matches.value_of(key).unwrap_or(""))
+}
+
+/// Builds the cache of Toggl API responses from `--cache_dir` and `--cache_ttl`
+///
+/// Returns None if `--no_cache` is given.
+fn response_cache(
+    matches: &ArgMatches,
+    today: NaiveDate,
+) -> Result<Option<ResponseCache>, std::num::ParseIntError> {
+    if matches.is_present("no_cache") {
+        return Ok(None);
+    }
+    let dir = matches
+        .value_of("cache_dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(ResponseCache::default_dir);
+    let ttl = matches
+        .value_of("cache_ttl")
+        .map(|minutes| minutes.parse::<i64>())
+        .transpose()?
+        .unwrap_or(10);
+    Ok(Some(ResponseCache::new(
+        dir,
+        chrono::Duration::minutes(ttl),
+        today,
+    )))
+}
+
+/// Fetches the reports of the workspaces concurrently
+///
+/// The same person may have different names in each workspace, so they are merged by email.
+async fn fetch_reports(
+    toggl_accessors: &[toggl::TogglAccessor],
+    period: &Period,
+    parallelism: usize,
+    needs_workspace_names: bool,
+) -> Result<Vec<(Workspace, toggl::TogglReport)>, Box<dyn std::error::Error>> {
+    let now = Utc::now();
+    let needs_workspace_users = toggl_accessors.len() > 1;
+    let fetched: Vec<(
+        Workspace,
+        Vec<toggl::TogglWorkspaceUser>,
+        toggl::TogglReport,
+    )> = stream::iter(toggl_accessors.iter().map(|accessor| async move {
+        let workspace = if needs_workspace_names {
+            Workspace::new(accessor.fetch_workspace().await?.name)
+        } else {
+            Workspace::new(accessor.workspace.as_str())
+        };
+        let users = if needs_workspace_users {
+            accessor.fetch_workspace_users().await?
+        } else {
+            vec![]
+        };
+        let report = accessor.fetch_report(period, now).await?;
+        Ok::<_, Box<dyn std::error::Error>>((workspace, users, report))
+    }))
+    .buffered(parallelism)
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<Result<_, _>>()?;
+    let users_by_workspace: Vec<_> = fetched.iter().map(|(_, users, _)| users.clone()).collect();
+    Ok(fetched
+        .into_iter()
+        .zip(toggl::TogglAccessor::unify_user_names(&users_by_workspace))
+        .map(|((workspace, _, report), names)| (workspace, report.rename_users(&names)))
+        .collect())
+}
+
+/// Saves the raw Reports API responses of the period to replay them with `--from_file`
+async fn run_fetch(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let period = Period::parse(
+        matches.value_of("date_from").unwrap_or(""),
+        matches.value_of("date_to").unwrap_or(""),
+    )?;
+    let date_from = period.begin.format("%Y-%m-%d").to_string();
+    let date_to = period.end.format("%Y-%m-%d").to_string();
+    let save_dir = PathBuf::from(matches.value_of("save").unwrap_or("."));
+    let workspaces: Vec<&str> = matches
+        .values_of("workspace")
+        .map(|values| values.collect())
+        .unwrap_or_else(|| vec![""]);
+    for workspace in workspaces {
+        let accessor = toggl::TogglAccessor {
+            token: matches.value_of("toggl_token").unwrap_or("").to_string(),
+            workspace: workspace.to_string(),
+            email: matches.value_of("toggl_email").unwrap_or("").to_string(),
+            save_dir: Some(save_dir.clone()),
+            ..Default::default()
+        };
+        accessor.fetch_summary_report(&date_from, &date_to).await?;
+        // the entries are saved with the margin which `--timezone` clips them by, like `fetch_report`
+        accessor
+            .fetch_details(
+                &(period.begin - chrono::Duration::days(1))
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                &(period.end + chrono::Duration::days(1))
+                    .format("%Y-%m-%d")
+                    .to_string(),
+            )
+            .await?;
+    }
+    println!("Saved to {}", save_dir.display());
+    Ok(())
+}
+
+/// Builds `<kind> list` subcommand
+fn list_subcommand<'a>(kind: &'a str, about: &'a str) -> App<'a> {
+    App::new(kind)
+        .about(about)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            App::new("list").about(about).arg(
+                Arg::new("json")
+                    .long("json")
+                    .about("Prints JSON instead of a table"),
+            ),
+        )
+}
+
+/// Fetches items of every workspace, tagging them with the workspace ID
+async fn fetch_workspace_items<'a, T, F, Fut>(
+    accessors: &'a [toggl::TogglAccessor],
+    fetch: F,
+) -> Result<Vec<WorkspaceItem<T>>, Box<dyn std::error::Error>>
+where
+    F: Fn(&'a toggl::TogglAccessor) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, Box<dyn std::error::Error>>>,
+{
+    let mut items = vec![];
+    for accessor in accessors {
+        items.extend(
+            fetch(accessor)
+                .await?
+                .into_iter()
+                .map(|item| WorkspaceItem {
+                    workspace_id: accessor.workspace.clone(),
+                    item,
+                }),
+        );
+    }
+    Ok(items)
+}
+
+/// Prints workspaces, users, projects, clients or tags to configure the report
+///
+/// Lists every workspace of the token owner unless `--workspace` is given.
+async fn run_list(kind: &str, matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let json = matches.is_present("json");
+    let accessor = toggl::TogglAccessor {
+        token: matches.value_of("toggl_token").unwrap_or("").to_string(),
+        ..Default::default()
+    };
+    let workspaces: Vec<String> = match matches.values_of("workspace") {
+        Some(values) => values.map(|w| w.to_string()).collect(),
+        None if kind == "workspaces" => vec![],
+        None => accessor
+            .fetch_workspaces()
+            .await?
+            .iter()
+            .map(|w| w.id.to_string())
+            .collect(),
+    };
+    let accessors: Vec<toggl::TogglAccessor> = workspaces
+        .into_iter()
+        .map(|workspace| toggl::TogglAccessor {
+            workspace,
+            ..accessor.clone()
+        })
+        .collect();
+    let output = match kind {
+        "workspaces" => listing::render(&accessor.fetch_workspaces().await?, json)?,
+        "users" => listing::render(
+            &fetch_workspace_items(&accessors, |a| a.fetch_workspace_users()).await?,
+            json,
+        )?,
+        "projects" => listing::render(
+            &fetch_workspace_items(&accessors, |a| a.fetch_projects()).await?,
+            json,
+        )?,
+        "clients" => listing::render(
+            &fetch_workspace_items(&accessors, |a| a.fetch_clients()).await?,
+            json,
+        )?,
+        "tags" => listing::render(
+            &fetch_workspace_items(&accessors, |a| a.fetch_tags()).await?,
+            json,
+        )?,
+        _ => unreachable!(),
+    };
+    print!("{}", output);
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -12,21 +255,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .version("1.0")
         .author("sankaku <sankaku.git@gmail.com>")
         .about("Fetch toggl report and send it to Slack")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(list_subcommand("workspaces", "Lists workspaces"))
+        .subcommand(list_subcommand("users", "Lists users of the workspaces"))
+        .subcommand(list_subcommand("projects", "Lists projects of the workspaces"))
+        .subcommand(list_subcommand("clients", "Lists clients of the workspaces"))
+        .subcommand(list_subcommand("tags", "Lists tags of the workspaces"))
+        .subcommand(
+            App::new("fetch")
+                .about("Saves raw Reports API responses to replay them with --from_file")
+                .arg(
+                    Arg::new("date_from")
+                        .long("date_from")
+                        .value_name("DATE_FROM")
+                        .about("Sets the start date of the period")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("date_to")
+                        .long("date_to")
+                        .value_name("DATE_TO")
+                        .about("Sets the end date of the period")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("toggl_email")
+                        .long("toggl_email")
+                        .value_name("TOGGL_MAIL_ADDRESS")
+                        .about("Sets email address for toggl")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("save")
+                        .long("save")
+                        .value_name("DIR")
+                        .about("Sets the directory to write the responses")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("cache")
+                .about("Manages the cache of Toggl API responses")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(App::new("clear").about("Removes all the cached responses")),
+        )
         .arg(
             Arg::new("toggl_token")
                 .short('t')
                 .long("toggl_token")
                 .value_name("TOGGL_API_TOKEN")
                 .about("Sets API token for toggl")
-                .takes_value(true),
+                .takes_value(true)
+                .global(true),
         )
         .arg(
             Arg::new("workspace")
                 .long("workspace")
                 .value_name("TOGGL_WORKSPACE")
-                .about("Sets workspace id for toggl")
+                .about("Sets workspace ids for toggl, separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("parallelism")
+                .long("parallelism")
+                .value_name("N")
+                .about("Sets the number of workspaces fetched concurrently (default: 2)")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("group_by_workspace")
+                .long("group_by_workspace")
+                .about("Groups users by workspace in the summary"),
+        )
+        .arg(
+            Arg::new("workspace_column")
+                .long("workspace_column")
+                .about("Adds the workspace column to the CSV"),
+        )
         .arg(
             Arg::new("toggl_email")
                 .long("toggl_email")
@@ -52,15 +360,327 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arg::new("slack_token")
                 .long("slack_token")
                 .value_name("Slack token")
-                .about("Sets Slack API token")
-                .required(true),
+                .about("Sets Slack API token"),
         )
         .arg(
             Arg::new("slack_channel")
                 .long("slack_channel")
                 .value_name("Slack channel")
-                .about("Sets Slack channel")
-                .required(true),
+                .about("Sets Slack channel"),
+        )
+        .arg(
+            Arg::new("post")
+                .long("post")
+                .requires_all(&["slack_token", "slack_channel"])
+                .about("Posts the messages and uploads the charts to the Slack channel"),
+        )
+        .arg(
+            Arg::new("billing")
+                .long("billing")
+                .about("Reports billable and non-billable hours and billed amounts as well"),
+        )
+        .arg(
+            Arg::new("billable_only")
+                .long("billable_only")
+                .about("Reports billable time entries only"),
+        )
+        .arg(
+            Arg::new("top_descriptions")
+                .long("top_descriptions")
+                .value_name("N")
+                .about("Lists the top N descriptions (and tasks) under each project in the summary")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("include_users")
+                .long("include_users")
+                .value_name("USER")
+                .about("Includes users by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("exclude_users")
+                .long("exclude_users")
+                .value_name("USER")
+                .about("Excludes users by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("include_projects")
+                .long("include_projects")
+                .value_name("PROJECT")
+                .about("Includes projects by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("exclude_projects")
+                .long("exclude_projects")
+                .value_name("PROJECT")
+                .about("Excludes projects by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("include_clients")
+                .long("include_clients")
+                .value_name("CLIENT")
+                .about("Includes clients by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("exclude_clients")
+                .long("exclude_clients")
+                .value_name("CLIENT")
+                .about("Excludes clients by ID or name (glob), separated by commas")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("timezone")
+                .long("timezone")
+                .value_name("TIMEZONE")
+                .about("Sets IANA timezone to bucket entries by date. eg. Asia/Tokyo")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("split_midnight")
+                .long("split_midnight")
+                .about("Splits entries crossing midnight into each date"),
+        )
+        .arg(
+            Arg::new("include_running")
+                .long("include_running")
+                .about("Includes the running time entries of the workspace users"),
+        )
+        .arg(
+            Arg::new("rounding")
+                .long("rounding")
+                .value_name("SPEC")
+                .about("Sets how durations are rounded and formatted. eg. mode=up,increment=6,style=hmm,places=2,totals=sum_rounded")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("summary_rounding")
+                .long("summary_rounding")
+                .value_name("SPEC")
+                .about("Overrides --rounding in the summary")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_rounding")
+                .long("csv_rounding")
+                .value_name("SPEC")
+                .about("Overrides --rounding in the CSV")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("billing_rounding")
+                .long("billing_rounding")
+                .value_name("SPEC")
+                .about("Overrides --rounding in the billing report")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_totals")
+                .long("csv_totals")
+                .value_name("KINDS")
+                .about("Adds totals to the CSV: row, project, user, grand or all, separated by commas")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_layout")
+                .long("csv_layout")
+                .value_name("LAYOUT")
+                .about("Sets the CSV layout: project_user (default), user_project or long")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_dates_as_rows")
+                .long("csv_dates_as_rows")
+                .about("Shows dates as rows instead of columns in the CSV"),
+        )
+        .arg(
+            Arg::new("csv_sparse")
+                .long("csv_sparse")
+                .about("Omits rows whose hours are all 0 in the CSV"),
+        )
+        .arg(
+            Arg::new("csv_bucket")
+                .long("csv_bucket")
+                .value_name("GRANULARITY")
+                .about("Sets the columns of the CSV: day (default), week or month")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("week_start")
+                .long("week_start")
+                .value_name("WEEKDAY")
+                .about("Sets the first day of week: monday (default) or sunday")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("locale")
+                .long("locale")
+                .value_name("LOCALE")
+                .about("Sets the language of messages and CSV: en (default) or ja")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_weekdays")
+                .long("csv_weekdays")
+                .about("Adds weekday names to the dates in the CSV header"),
+        )
+        .arg(
+            Arg::new("holidays")
+                .long("holidays")
+                .value_name("PATH")
+                .about("Marks holidays in the CSV header and skips them in the alerts: jp (bundled Japanese holidays), an iCalendar file (.ics) or a CSV file of date,name")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::new("weekends")
+                .long("weekends")
+                .value_name("DAYS")
+                .about("Sets the comma-separated days of weekends (default: sat,sun)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("csv_hide_days_off")
+                .long("csv_hide_days_off")
+                .about("Omits weekends and holidays without time from the columns of the CSV"),
+        )
+        .arg(
+            Arg::new("shares")
+                .long("shares")
+                .about("Shows the share of each project, sorting projects by hours, and ranks projects of everyone"),
+        )
+        .arg(
+            Arg::new("bars")
+                .long("bars")
+                .about("Draws bar charts of hours for each project in the summary"),
+        )
+        .arg(
+            Arg::new("bar_width")
+                .long("bar_width")
+                .value_name("CELLS")
+                .about("Sets the width of the longest bar (default: 20)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("summary_template")
+                .long("summary_template")
+                .value_name("PATH")
+                .about("Renders the summary by the Handlebars template file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("expected_hours")
+                .long("expected_hours")
+                .value_name("PATH")
+                .about("Adds alerts of days with too few hours and weeks over the maximum by the JSON file of expected hours")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("mention")
+                .long("mention")
+                .about("Mentions users in the alerts by their slack_id in the file of expected hours"),
+        )
+        .arg(
+            Arg::new("budgets")
+                .long("budgets")
+                .value_name("PATH")
+                .about("Adds the consumption of project budgets by the JSON file of budgets")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("compare")
+                .long("compare")
+                .about("Compares hours with the previous period of the same length, e.g. the previous week or month"),
+        )
+        .arg(
+            Arg::new("charts")
+                .long("charts")
+                .about("Draws PNG charts of hours per day and the share of projects"),
+        )
+        .arg(
+            Arg::new("chart_font")
+                .long("chart_font")
+                .value_name("PATH")
+                .about("Sets the TrueType or OpenType font of the charts (default: DejaVu Sans or Noto Sans CJK if installed)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("chart_dir")
+                .long("chart_dir")
+                .value_name("DIR")
+                .about("Writes the PNG charts to the directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .about("Also writes the report as a document of html, markdown or json (default: slack, which writes no document)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("PATH")
+                .about("Sets the path of the document (default: report_<date_from>_<date_to>.<html|md|json>)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("toggl_summary")
+                .long("toggl_summary")
+                .about("Uses Summary API for the summary instead of summing up the time entries"),
+        )
+        .arg(
+            Arg::new("check_summary")
+                .long("check_summary")
+                .about("Warns about differences between Summary API and the time entries"),
+        )
+        .arg(
+            Arg::new("from_file")
+                .long("from_file")
+                .alias("from-file")
+                .value_name("PATH")
+                .about("Renders the report from saved responses, Toggl CSV exports or directories of them without Toggl API")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .use_delimiter(true),
+        )
+        .arg(
+            Arg::new("no_cache")
+                .long("no_cache")
+                .alias("no-cache")
+                .about("Fetches everything from Toggl API without the cache"),
+        )
+        .arg(
+            Arg::new("cache_dir")
+                .long("cache_dir")
+                .value_name("DIR")
+                .about("Sets the cache directory (default: $XDG_CACHE_HOME/toggl2slack or ~/.cache/toggl2slack)")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("cache_ttl")
+                .long("cache_ttl")
+                .value_name("MINUTES")
+                .about("Sets how long responses for yesterday and later are cached (default: 10)")
+                .takes_value(true),
         )
         .arg(
             Arg::new("v")
@@ -70,34 +690,396 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .get_matches();
 
+    if let Some((kind, kind_matches)) = matches.subcommand() {
+        return match (kind, kind_matches.subcommand()) {
+            ("fetch", _) => run_fetch(kind_matches).await,
+            ("cache", Some((_, clear_matches))) => {
+                let dir = clear_matches
+                    .value_of("cache_dir")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(ResponseCache::default_dir);
+                println!("Removing {}", dir.display());
+                let today = Local::today().naive_local();
+                Ok(ResponseCache::new(dir, chrono::Duration::zero(), today).clear()?)
+            }
+            (_, Some((_, list_matches))) => run_list(kind, list_matches).await,
+            _ => Ok(()),
+        };
+    }
+
     let toggl_token = matches.value_of("toggl_token").unwrap_or("");
-    let workspace = matches.value_of("workspace").unwrap_or("");
+    let workspaces: Vec<&str> = matches
+        .values_of("workspace")
+        .map(|values| values.collect())
+        .unwrap_or_else(|| vec![""]);
+    let parallelism = matches
+        .value_of("parallelism")
+        .map(|n| n.parse::<usize>())
+        .transpose()?
+        .unwrap_or(2)
+        .max(1);
+    let toggl_summary = matches.is_present("toggl_summary");
+    let check_summary = matches.is_present("check_summary");
+    let group_by_workspace = matches.is_present("group_by_workspace");
+    let workspace_column = matches.is_present("workspace_column");
     let toggl_email = matches.value_of("toggl_email").unwrap_or("");
-    let date_from = matches.value_of("date_from").unwrap_or("");
-    let date_to = matches.value_of("date_to").unwrap_or("");
-    let slack_token = matches.value_of("slack_token").unwrap_or("");
-    let slack_channel = matches.value_of("slack_channel").unwrap_or("");
-
-    let toggl_accessor = toggl::TogglAccessor {
-        token: toggl_token.to_string(),
-        workspace: workspace.to_string(),
-        email: toggl_email.to_string(),
+    let timezone = matches
+        .value_of("timezone")
+        .map(|tz| tz.parse::<Tz>())
+        .transpose()?;
+    let split_midnight = matches.is_present("split_midnight");
+    let include_running = matches.is_present("include_running");
+    let today = match &timezone {
+        Some(tz) => Utc::now().with_timezone(tz).date().naive_local(),
+        None => Local::today().naive_local(),
+    };
+    let period = Period::parse(
+        matches.value_of("date_from").unwrap_or(""),
+        matches.value_of("date_to").unwrap_or(""),
+    )?;
+    let date_from = &period.begin.format("%Y-%m-%d").to_string();
+    let date_to = &period.end.format("%Y-%m-%d").to_string();
+    let locale = matches
+        .value_of("locale")
+        .unwrap_or("en")
+        .parse::<Locale>()?;
+    // templates are validated before fetching reports
+    let summary_template = matches
+        .value_of("summary_template")
+        .map(|path| SummaryTemplate::from_file(&PathBuf::from(path)))
+        .transpose()?
+        .unwrap_or_default();
+    let expected_hours = matches
+        .value_of("expected_hours")
+        .map(|path| ExpectedHours::from_file(&PathBuf::from(path)))
+        .transpose()?;
+    let week_start =
+        Bucketing::parse_week_start(matches.value_of("week_start").unwrap_or("monday"))?;
+    let budgets = matches
+        .value_of("budgets")
+        .map(|path| Budgets::from_file(&PathBuf::from(path)))
+        .transpose()?;
+    // weekends and holidays are taken into account only if either is given
+    let calendar = if matches.is_present("holidays") || matches.is_present("weekends") {
+        let mut calendar = Calendar::default();
+        if let Some(weekends) = matches.value_of("weekends") {
+            calendar.weekends = Calendar::parse_weekends(weekends)?;
+        }
+        for source in matches.values_of("holidays").into_iter().flatten() {
+            calendar.add_holidays(source)?;
+        }
+        Some(calendar)
+    } else {
+        None
+    };
+    let compare = matches.is_present("compare");
+    let format = matches
+        .value_of("format")
+        .unwrap_or("slack")
+        .parse::<OutputFormat>()?;
+    let charts = matches.is_present("charts") || matches.is_present("chart_dir");
+    let chart_font = match matches.value_of("chart_font") {
+        Some(path) => Some(ChartRenderer::load_font(&PathBuf::from(path))?),
+        None if charts => ChartRenderer::default_font(),
+        None => None,
     };
-    let summary_report = toggl_accessor
-        .fetch_summary_report(date_from, date_to)
-        .await?;
-    let detailed_report = toggl_accessor
-        .fetch_detailed_report(date_from, date_to)
-        .await?;
-
-    let start_date: NaiveDate = NaiveDate::parse_from_str(date_from, "%Y-%m-%d").unwrap();
-    let end_date: NaiveDate = NaiveDate::parse_from_str(date_to, "%Y-%m-%d").unwrap();
-
-    let message_creator = message::MessageCreator {};
-    let summary_message =
-        message_creator.get_project_message(&summary_report, &start_date, &end_date);
-    let detailed_message =
-        message_creator.create_text_for_csv(&detailed_report, &start_date, &end_date);
+    let billing = matches.is_present("billing");
+    let billable_only = matches.is_present("billable_only");
+    let top_descriptions = matches
+        .value_of("top_descriptions")
+        .map(|n| n.parse::<usize>())
+        .transpose()?;
+    let filter = ReportFilter::new(
+        parse_rule(&matches, "users")?,
+        parse_rule(&matches, "projects")?,
+        parse_rule(&matches, "clients")?,
+    );
+
+    let cache = response_cache(&matches, today)?;
+
+    let mut toggl_accessors: Vec<toggl::TogglAccessor> = workspaces
+        .iter()
+        .map(|workspace| toggl::TogglAccessor {
+            token: toggl_token.to_string(),
+            workspace: workspace.to_string(),
+            email: toggl_email.to_string(),
+            billable_only,
+            filter: filter.clone(),
+            timezone,
+            split_at_midnight: split_midnight,
+            include_running,
+            cache: cache.clone(),
+            save_dir: None,
+            toggl_summary,
+            check_summary,
+        })
+        .collect();
+    // client IDs are not in the time entries, so they are matched by the names of the clients
+    if filter.needs_client_names() {
+        if matches.is_present("from_file") {
+            return Err(
+                "Clients cannot be filtered by ID with --from_file. Use the client name instead"
+                    .into(),
+            );
+        }
+        for accessor in &mut toggl_accessors {
+            let clients = accessor.fetch_clients().await?;
+            accessor.filter = accessor.filter.clone().with_clients(&clients);
+        }
+    }
+    let previous_period = period.previous();
+    // budgets are summed up from the report unless they begin before the period
+    let budget_period = budgets
+        .as_ref()
+        .map(|budgets| budgets.fetch_period(&period.end))
+        .filter(|budget_period| budget_period.begin < period.begin);
+    let (reports_by_workspace, previous_reports, budget_report) = match matches
+        .values_of("from_file")
+    {
+        Some(paths) => {
+            // saved responses cover the period only, so the reports of other periods would lack entries
+            if compare {
+                return Err("--compare cannot be used with --from_file".into());
+            }
+            if budget_period.is_some() {
+                return Err(
+                    "--budgets beginning before the period cannot be used with --from_file".into(),
+                );
+            }
+            let paths: Vec<PathBuf> = paths.map(PathBuf::from).collect();
+            let saved = SavedReport::load(&paths, timezone.as_ref())?;
+            let summary = if saved.summaries.is_empty() {
+                None
+            } else {
+                Some(saved.summary())
+            };
+            let report = toggl_accessors[0].build_report(summary, saved.details, vec![], &period);
+            (vec![(Workspace::new("saved"), report)], None, None)
+        }
+        None => {
+            let needs_workspace_names =
+                group_by_workspace || workspace_column || format == OutputFormat::Json;
+            let reports = fetch_reports(
+                &toggl_accessors,
+                &period,
+                parallelism,
+                needs_workspace_names,
+            )
+            .await?;
+            let previous_reports = if compare {
+                let previous_reports = fetch_reports(
+                    &toggl_accessors,
+                    &previous_period,
+                    parallelism,
+                    needs_workspace_names,
+                )
+                .await?;
+                Some(previous_reports.into_iter().map(|(_, r)| r).collect())
+            } else {
+                None
+            };
+            let budget_report = match budget_period {
+                Some(budget_period) => {
+                    let budget_reports =
+                        fetch_reports(&toggl_accessors, &budget_period, parallelism, false).await?;
+                    Some(toggl::TogglReport::merge(
+                        budget_reports.into_iter().map(|(_, r)| r).collect(),
+                    ))
+                }
+                None => None,
+            };
+            (reports, previous_reports, budget_report)
+        }
+    };
+    let previous_report = previous_reports.clone().map(toggl::TogglReport::merge);
+    let report = toggl::TogglReport::merge(
+        reports_by_workspace
+            .iter()
+            .map(|(_, report)| report.clone())
+            .collect(),
+    );
+    let to_record_keys = |details: &[toggl::TogglDetail]| -> Vec<_> {
+        toggl::TogglAccessor::convert_details_to_vec_in_timezone(
+            details,
+            timezone.as_ref(),
+            split_midnight,
+        )
+        .into_iter()
+        .filter(|(k, _)| period.contains(&k.date))
+        .collect()
+    };
+    let to_annotations = |report: &toggl::TogglReport,
+                          previous_report: Option<&toggl::TogglReport>| {
+        message::SummaryAnnotations {
+            descriptions: top_descriptions.map(|top_n| {
+                toggl::TogglAccessor::convert_details_to_description_records(&report.details, top_n)
+            }),
+            running: report
+                .running_details
+                .iter()
+                .map(|d| (d.user.clone(), d.project.clone()))
+                .collect(),
+            previous: previous_report.map(|r| r.summary.clone()),
+            billing: if billing {
+                Some(toggl::TogglAccessor::convert_details_to_billing_records(
+                    &report.details,
+                ))
+            } else {
+                None
+            },
+        }
+    };
+
+    let start_date: NaiveDate = period.begin;
+    let end_date: NaiveDate = period.end;
+
+    let message_creator = message::MessageCreator {
+        summary_rounding: rounding_policy(&matches, "summary_rounding", locale)?,
+        csv_rounding: rounding_policy(&matches, "csv_rounding", locale)?,
+        billing_rounding: rounding_policy(&matches, "billing_rounding", locale)?,
+        billing,
+        csv_totals: matches
+            .value_of("csv_totals")
+            .unwrap_or("")
+            .parse::<message::CsvTotals>()?,
+        csv_layout: matches
+            .value_of("csv_layout")
+            .map(|layout| layout.parse::<message::CsvLayout>())
+            .transpose()?
+            .unwrap_or_default(),
+        csv_dates_as_rows: matches.is_present("csv_dates_as_rows"),
+        csv_sparse: matches.is_present("csv_sparse"),
+        csv_bucketing: Bucketing {
+            granularity: matches
+                .value_of("csv_bucket")
+                .unwrap_or("day")
+                .parse::<Granularity>()?,
+            week_start,
+        },
+        summary_template,
+        locale,
+        csv_weekdays: matches.is_present("csv_weekdays"),
+        calendar,
+        csv_hide_days_off: matches.is_present("csv_hide_days_off"),
+        summary_shares: matches.is_present("shares"),
+        summary_chart: if matches.is_present("bars") {
+            Some(BarChart {
+                width: matches
+                    .value_of("bar_width")
+                    .map(|n| n.parse::<usize>())
+                    .transpose()?
+                    .unwrap_or(20),
+            })
+        } else {
+            None
+        },
+    };
+    message_creator.check_options()?;
+    let summary_context = if group_by_workspace {
+        let reports: Vec<_> = reports_by_workspace
+            .iter()
+            .enumerate()
+            .map(|(i, (workspace, report))| {
+                (
+                    workspace.clone(),
+                    report.summary.clone(),
+                    to_annotations(report, previous_reports.as_ref().map(|r| &r[i])),
+                )
+            })
+            .collect();
+        message_creator.get_summary_context_by_workspace(&reports, &start_date, &end_date)
+    } else {
+        message_creator.get_summary_context(
+            &report.summary,
+            &to_annotations(&report, previous_report.as_ref()),
+            &start_date,
+            &end_date,
+        )
+    };
+    let detailed_table = if workspace_column {
+        let records: Vec<_> = reports_by_workspace
+            .iter()
+            .map(|(workspace, report)| (workspace.clone(), to_record_keys(&report.details)))
+            .collect();
+        let billings: Option<HashMap<_, _>> = if billing {
+            Some(
+                reports_by_workspace
+                    .iter()
+                    .map(|(workspace, report)| {
+                        (
+                            workspace.clone(),
+                            toggl::TogglAccessor::convert_details_to_billing_records(
+                                &report.details,
+                            ),
+                        )
+                    })
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        message_creator.get_detailed_table_by_workspace(
+            &records,
+            billings.as_ref(),
+            &start_date,
+            &end_date,
+        )
+    } else {
+        let billings = if billing {
+            Some(toggl::TogglAccessor::convert_details_to_billing_records(
+                &report.details,
+            ))
+        } else {
+            None
+        };
+        message_creator.get_detailed_table(
+            &to_record_keys(&report.details),
+            billings.as_ref(),
+            &start_date,
+            &end_date,
+        )
+    };
+    let summary_message = message_creator.summary_template.render(&summary_context)?;
+    let detailed_message = message_creator.write_csv_text(detailed_table.clone());
+
+    // documents are written besides the Slack messages to archive or post-process reports
+    if format != OutputFormat::Slack {
+        let document = Document {
+            summary: &summary_context,
+            detailed: &detailed_table,
+            locale,
+        };
+        let (text, extension) = match format {
+            OutputFormat::Html => (document.to_html(), "html"),
+            OutputFormat::Json => {
+                let workspaces: Vec<WorkspaceRecords> = reports_by_workspace
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (workspace, report))| WorkspaceRecords {
+                        workspace,
+                        // saved reports do not tell their workspaces
+                        workspace_id: if matches.is_present("from_file") {
+                            None
+                        } else {
+                            toggl_accessors[i].workspace.parse::<u64>().ok()
+                        },
+                        details: &report.details,
+                        records: to_record_keys(&report.details),
+                    })
+                    .collect();
+                (JsonReport::new(&period, &workspaces).to_json()?, "json")
+            }
+            _ => (document.to_markdown(), "md"),
+        };
+        let path = matches
+            .value_of("output")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                PathBuf::from(format!("report_{}_{}.{}", date_from, date_to, extension))
+            });
+        std::fs::write(&path, text)?;
+        println!("[document] {}", path.display());
+    }
 
     print!(
         "[summary_message in {date_from} to {date_to}]\n{summary_message}",
@@ -111,12 +1093,108 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         date_to = &date_to,
         detailed_message = &detailed_message
     );
+    let mut messages = vec![summary_message, detailed_message];
 
-    let sender = slack::SlackAccessor {
-        token: slack_token.to_string(),
-    };
-    // sender.send_message(slack_channel, &summary_message).await?;
-    // sender.send_message(slack_channel, &detailed_message).await?;
+    if let Some(expected_hours) = &expected_hours {
+        let alerts = expected_hours.check(
+            &to_record_keys(&report.details),
+            &period,
+            &today,
+            week_start,
+            message_creator.calendar.as_ref(),
+        );
+        if !alerts.is_empty() {
+            let alert_message = message_creator.get_alert_message(
+                &alerts,
+                matches.is_present("mention"),
+                &start_date,
+                &end_date,
+            );
+            print!(
+                "[alert_message in {date_from} to {date_to}]\n{alert_message}",
+                date_from = &date_from,
+                date_to = &date_to,
+                alert_message = &alert_message
+            );
+            messages.push(alert_message);
+        }
+    }
+
+    if let Some(budgets) = &budgets {
+        let details = &budget_report.as_ref().unwrap_or(&report).details;
+        let records = toggl::TogglAccessor::convert_details_to_vec_in_timezone(
+            details,
+            timezone.as_ref(),
+            split_midnight,
+        );
+        let budget_message = message_creator.get_budget_message(
+            &budgets.status(&records, &end_date),
+            &start_date,
+            &end_date,
+        );
+        print!(
+            "[budget_message in {date_from} to {date_to}]\n{budget_message}",
+            date_from = &date_from,
+            date_to = &date_to,
+            budget_message = &budget_message
+        );
+        messages.push(budget_message);
+    }
+
+    let mut images = vec![];
+    if charts {
+        let mut colors = ProjectColors::default();
+        // saved reports have no projects, so that their colours are derived from names
+        if !matches.is_present("from_file") {
+            for accessor in &toggl_accessors {
+                for project in accessor.fetch_projects().await? {
+                    if let Some(hex_color) = &project.hex_color {
+                        colors.insert(&project.name, hex_color);
+                    }
+                }
+            }
+        }
+        let renderer = ChartRenderer {
+            colors,
+            font: chart_font,
+        };
+        let records = to_record_keys(&report.details);
+        let labels = locale.labels();
+        images.push((
+            format!("hours_{}_{}.png", date_from, date_to),
+            labels.chart_hours_title,
+            renderer.stacked_bars(&records, &start_date, &end_date)?,
+        ));
+        images.push((
+            format!("share_{}_{}.png", date_from, date_to),
+            labels.chart_share_title,
+            renderer.pie(&records)?,
+        ));
+        if let Some(dir) = matches.value_of("chart_dir") {
+            for (filename, _, bytes) in &images {
+                let path = PathBuf::from(dir).join(filename);
+                std::fs::write(&path, bytes)?;
+                println!("[chart] {}", path.display());
+            }
+        }
+    }
+
+    if matches.is_present("post") {
+        let sender = slack::SlackAccessor {
+            token: matches.value_of("slack_token").unwrap_or("").to_string(),
+        };
+        let slack_channel = matches.value_of("slack_channel").unwrap_or("");
+        for message in &messages {
+            sender.send_message(slack_channel, message).await?;
+        }
+        if matches.is_present("charts") {
+            for (filename, title, bytes) in images {
+                sender
+                    .upload_file(slack_channel, &filename, title, bytes)
+                    .await?;
+            }
+        }
+    }
 
     Ok(())
 }
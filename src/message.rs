@@ -1,51 +1,714 @@
+use crate::bucket::{Bucket, Bucketing, Granularity};
+use crate::budget::BudgetStatus;
+use crate::calendar::Calendar;
+use crate::chart::BarChart;
+use crate::expected::{LoggingAlert, UserAlerts};
+use crate::locale::Locale;
+use crate::rounding::RoundingPolicy;
+use crate::template::{
+    DescriptionContext, ProjectContext, RankingContext, SummaryContext, SummaryTemplate,
+    UserContext, WorkspaceContext,
+};
 use crate::toggl::RecordKey;
-use crate::values::{Duration, Project, ProjectRecords, User};
+use crate::values::{
+    Billing, BillingRecords, Currency, DescriptionRecords, Duration, Project, ProjectRecords, User,
+    Workspace,
+};
 use chrono::prelude::*;
 use csv::WriterBuilder;
 use itertools::Itertools;
+use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+/// Additional information shown in the summary message
+#[derive(Debug, Default)]
+pub struct SummaryAnnotations {
+    /// Top descriptions listed under each project
+    pub descriptions: Option<DescriptionRecords>,
+    /// Users and projects which have a running time entry
+    pub running: BTreeSet<(User, Project)>,
+    /// Durations in the previous period, which are compared with the report if given
+    ///
+    /// Projects of the previous period are listed with 0h if they have no time now,
+    /// while users who have no time now are not listed.
+    pub previous: Option<ProjectRecords>,
+    /// Billable and non-billable durations and billed amounts, which are shown if `MessageCreator::billing`
+    pub billing: Option<BillingRecords>,
+}
+
+/// Totals added to the detailed CSV
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CsvTotals {
+    /// Total column of each row
+    pub row: bool,
+    /// Total row per date of each project
+    pub project: bool,
+    /// Subtotal rows per date of each user
+    pub user: bool,
+    /// Grand-total row per date
+    pub grand: bool,
+}
+
+impl FromStr for CsvTotals {
+    type Err = String;
+
+    /// Parses comma-separated kinds of totals, e.g. "row,project", or "all"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut totals = CsvTotals::default();
+        for kind in s.split(',').map(|k| k.trim()).filter(|k| !k.is_empty()) {
+            match kind {
+                "row" => totals.row = true,
+                "project" => totals.project = true,
+                "user" => totals.user = true,
+                "grand" => totals.grand = true,
+                "all" => {
+                    totals = CsvTotals {
+                        row: true,
+                        project: true,
+                        user: true,
+                        grand: true,
+                    }
+                }
+                _ => return Err(format!("Unknown kind of totals: {}", kind)),
+            }
+        }
+        Ok(totals)
+    }
+}
+
+/// Rows and label columns of the detailed CSV
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsvLayout {
+    /// Project and User columns, grouped by project
+    ProjectUser,
+    /// User and Project columns, grouped by user
+    UserProject,
+    /// One row per date, user and project like `date,user,project,hours`
+    Long,
+}
+
+impl Default for CsvLayout {
+    fn default() -> Self {
+        CsvLayout::ProjectUser
+    }
+}
 
-pub struct MessageCreator {}
+impl FromStr for CsvLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "project_user" => Ok(CsvLayout::ProjectUser),
+            "user_project" => Ok(CsvLayout::UserProject),
+            "long" => Ok(CsvLayout::Long),
+            _ => Err(format!("Unknown CSV layout: {}", s)),
+        }
+    }
+}
+
+/// Labels of a row of the detailed CSV with its durations per bucket and the billing of the period
+type CsvCells = (String, String, Vec<Duration>, Billing);
+
+/// Values of a workspace with the durations of its projects, and those in the previous period if compared
+type WorkspaceTimes = (
+    WorkspaceContext,
+    Vec<(Project, Duration)>,
+    Option<Vec<(Project, Duration)>>,
+);
+
+/// Renders reports into Slack messages and CSV
+///
+/// Durations are rounded and formatted by the policy of each renderer.
+#[derive(Debug, Default)]
+pub struct MessageCreator {
+    pub summary_rounding: RoundingPolicy,
+    pub csv_rounding: RoundingPolicy,
+    pub billing_rounding: RoundingPolicy,
+    pub csv_totals: CsvTotals,
+    pub csv_layout: CsvLayout,
+    /// If true, dates are rows and the pairs of project and user are columns
+    pub csv_dates_as_rows: bool,
+    /// If true, rows whose durations are all 0 are omitted
+    pub csv_sparse: bool,
+    /// Columns of the CSV, which are days by default
+    pub csv_bucketing: Bucketing,
+    pub summary_template: SummaryTemplate,
+    pub locale: Locale,
+    /// If true, weekday names are added to the dates in the CSV header
+    pub csv_weekdays: bool,
+    /// If true, the summary shows the share of each project and ranks projects by hours
+    pub summary_shares: bool,
+    /// Bar chart drawn for each project in the summary, which is not drawn if None
+    pub summary_chart: Option<BarChart>,
+    /// Weekends and holidays marked in the CSV header, which are not marked if None
+    pub calendar: Option<Calendar>,
+    /// If true, days off of the calendar are omitted from the CSV unless someone logged time on them
+    pub csv_hide_days_off: bool,
+    /// If true, billable and non-billable hours and billed amounts are added to the summary and the CSV
+    pub billing: bool,
+}
 impl MessageCreator {
-    /// Formats project-duration vector to string
+    /// Returns an error for the options which cannot be combined
+    pub fn check_options(&self) -> Result<(), String> {
+        if self.csv_layout == CsvLayout::Long {
+            if self.billing {
+                return Err("Billing columns cannot be added to the long CSV layout".to_string());
+            }
+            if self.csv_totals != CsvTotals::default() {
+                return Err("Totals cannot be added to the long CSV layout".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats project-duration vector to string by the summary template
     pub fn get_project_message(
         &self,
         project_times_by_user: &ProjectRecords,
         begin_date: &NaiveDate,
         end_date: &NaiveDate,
-    ) -> String {
-        let title = self.get_project_message_title(&begin_date, &end_date);
-        project_times_by_user
-            .value
+    ) -> Result<String, String> {
+        self.get_project_message_with_annotations(
+            project_times_by_user,
+            &SummaryAnnotations::default(),
+            begin_date,
+            end_date,
+        )
+    }
+
+    /// Formats project-duration vector to string with the annotations by the summary template
+    pub fn get_project_message_with_annotations(
+        &self,
+        project_times_by_user: &ProjectRecords,
+        annotations: &SummaryAnnotations,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<String, String> {
+        self.summary_template.render(&self.get_summary_context(
+            project_times_by_user,
+            annotations,
+            begin_date,
+            end_date,
+        ))
+    }
+
+    /// Returns the values of the summary, which are rendered into other formats as well
+    pub fn get_summary_context(
+        &self,
+        project_times_by_user: &ProjectRecords,
+        annotations: &SummaryAnnotations,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> SummaryContext {
+        let workspaces = vec![self.get_project_message_workspace_context(
+            "",
+            project_times_by_user,
+            annotations,
+        )];
+        self.get_project_message_context(workspaces, begin_date, end_date)
+    }
+
+    /// Formats project-duration vectors to string by the summary template, grouping users by workspace
+    pub fn get_project_message_by_workspace(
+        &self,
+        reports: &[(Workspace, ProjectRecords, SummaryAnnotations)],
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<String, String> {
+        self.summary_template
+            .render(&self.get_summary_context_by_workspace(reports, begin_date, end_date))
+    }
+
+    /// Returns the values of the summary grouping users by workspace
+    pub fn get_summary_context_by_workspace(
+        &self,
+        reports: &[(Workspace, ProjectRecords, SummaryAnnotations)],
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> SummaryContext {
+        let workspaces = reports
             .iter()
-            .fold(String::from(title), |acc, (u, project_times)| {
-                acc + &self.get_project_message_user_entry(&u, &project_times)
+            .map(|(w, project_times_by_user, annotations)| {
+                self.get_project_message_workspace_context(
+                    &w.to_string(),
+                    project_times_by_user,
+                    annotations,
+                )
             })
+            .collect();
+        self.get_project_message_context(workspaces, begin_date, end_date)
+    }
+
+    fn get_project_message_context(
+        &self,
+        workspaces: Vec<WorkspaceTimes>,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> SummaryContext {
+        let project_times: Vec<(Project, Duration)> = workspaces
+            .iter()
+            .flat_map(|(_, project_times, _)| project_times.iter().cloned())
+            .collect();
+        // the previous period is compared if any workspace has it
+        let previous_times: Option<Vec<(Project, Duration)>> = workspaces
+            .iter()
+            .filter_map(|(_, _, previous_times)| previous_times.as_ref())
+            .fold(None, |acc: Option<Vec<_>>, previous_times| {
+                let mut acc = acc.unwrap_or_default();
+                acc.extend(previous_times.iter().cloned());
+                Some(acc)
+            });
+        let durations: Vec<Duration> = project_times.iter().map(|(_, dur)| *dur).collect();
+        let delta = previous_times
+            .as_ref()
+            .map(|previous_times| {
+                let previous: Vec<Duration> = previous_times.iter().map(|(_, dur)| *dur).collect();
+                self.format_delta(
+                    &self.summary_rounding.total(&durations),
+                    &self.summary_rounding.total(&previous),
+                )
+            })
+            .unwrap_or_default();
+        let mut context = SummaryContext {
+            labels: *self.locale.labels(),
+            begin: *begin_date,
+            end: *end_date,
+            total: self.format_summary_total(&durations),
+            delta,
+            shares: self.summary_shares,
+            bars: self.summary_chart.is_some(),
+            comparison: previous_times.is_some(),
+            billing: self.billing,
+            workspaces: workspaces.into_iter().map(|(w, _, _)| w).collect(),
+            projects: self.get_project_message_ranking(&project_times, previous_times.as_deref()),
+        };
+        self.label_project_message_context(&mut context);
+        context
+    }
+
+    /// Pads the names of projects in each list and draws bars if the chart is enabled
+    ///
+    /// Bars of users are relative to the longest time of all the users so that users are comparable,
+    /// while bars of the ranking are relative to the top project.
+    fn label_project_message_context(&self, context: &mut SummaryContext) {
+        let users_max = context
+            .workspaces
+            .iter()
+            .flat_map(|w| w.users.iter())
+            .flat_map(|u| u.projects.iter())
+            .map(|p| p.duration)
+            .max_by_key(|dur| dur.value)
+            .unwrap_or_default();
+        for user in context
+            .workspaces
+            .iter_mut()
+            .flat_map(|w| w.users.iter_mut())
+        {
+            let names: Vec<String> = user.projects.iter().map(|p| p.name.clone()).collect();
+            for (project, label) in user.projects.iter_mut().zip(BarChart::pad_labels(&names)) {
+                project.label = label;
+                if let Some(chart) = &self.summary_chart {
+                    project.bar = chart.bar(&project.duration, &users_max);
+                }
+            }
+        }
+        let ranking_max = context
+            .projects
+            .first()
+            .map(|p| p.duration)
+            .unwrap_or_default();
+        let names: Vec<String> = context.projects.iter().map(|p| p.name.clone()).collect();
+        for (project, label) in context
+            .projects
+            .iter_mut()
+            .zip(BarChart::pad_labels(&names))
+        {
+            project.label = label;
+            if let Some(chart) = &self.summary_chart {
+                project.bar = chart.bar(&project.duration, &ranking_max);
+            }
+        }
+    }
+
+    /// Ranks projects of everyone by hours with the share of all the tracked time
+    ///
+    /// Projects of the previous period are ranked with 0h if they have no time now.
+    fn get_project_message_ranking(
+        &self,
+        project_times: &[(Project, Duration)],
+        previous_times: Option<&[(Project, Duration)]>,
+    ) -> Vec<RankingContext> {
+        let whole = self.sum_durations(project_times.iter().map(|(_, dur)| dur));
+        let previous_by_project: Option<HashMap<Project, Duration>> =
+            previous_times.map(|previous_times| {
+                previous_times
+                    .iter()
+                    .cloned()
+                    .into_group_map()
+                    .into_iter()
+                    .map(|(p, durations)| (p, self.sum_durations(durations.iter())))
+                    .collect()
+            });
+        let absent_times: Vec<(Project, Duration)> = previous_by_project
+            .iter()
+            .flat_map(|by_project| by_project.keys())
+            .map(|p| (p.clone(), Duration::new(0)))
+            .collect();
+        project_times
+            .iter()
+            .chain(absent_times.iter())
+            .cloned()
+            .into_group_map()
+            .into_iter()
+            .map(|(p, durations)| (p, self.sum_durations(durations.iter())))
+            .sorted_by(|(p1, dur1), (p2, dur2)| {
+                dur2.value.cmp(&dur1.value).then_with(|| p1.cmp(p2))
+            })
+            .enumerate()
+            .map(|(i, (p, dur))| RankingContext {
+                rank: i + 1,
+                name: p.to_string(),
+                label: p.to_string(),
+                time: self.summary_rounding.format(&dur),
+                bar: String::new(),
+                duration: dur,
+                share: self.format_share(&dur, &whole),
+                delta: previous_by_project
+                    .as_ref()
+                    .map(|by_project| {
+                        self.format_project_delta(
+                            &dur,
+                            by_project.get(&p).unwrap_or(&Duration::new(0)),
+                        )
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Returns the values of the workspace and the durations of all the projects in it
+    fn get_project_message_workspace_context(
+        &self,
+        name: &str,
+        project_times_by_user: &ProjectRecords,
+        annotations: &SummaryAnnotations,
+    ) -> WorkspaceTimes {
+        let project_times: Vec<(Project, Duration)> = project_times_by_user
+            .value
+            .values()
+            .flat_map(|project_times| project_times.iter().cloned())
+            .collect();
+        let durations: Vec<Duration> = project_times.iter().map(|(_, dur)| *dur).collect();
+        let workspace = WorkspaceContext {
+            name: name.to_string(),
+            total: self.format_summary_total(&durations),
+            users: project_times_by_user
+                .value
+                .iter()
+                .map(|(u, project_times)| {
+                    self.get_project_message_user_context(u, project_times, annotations)
+                })
+                .collect(),
+        };
+        let previous_times = annotations.previous.as_ref().map(|previous| {
+            previous
+                .value
+                .values()
+                .flat_map(|project_times| project_times.iter().cloned())
+                .collect()
+        });
+        (workspace, project_times, previous_times)
+    }
+
+    fn get_project_message_user_context(
+        &self,
+        user: &User,
+        project_times: &[(Project, Duration)],
+        annotations: &SummaryAnnotations,
+    ) -> UserContext {
+        let descriptions_by_project = annotations
+            .descriptions
+            .as_ref()
+            .and_then(|descriptions_by_user| descriptions_by_user.value.get(user));
+        let previous_times: Option<&[(Project, Duration)]> =
+            annotations.previous.as_ref().map(|previous| {
+                previous
+                    .value
+                    .get(user)
+                    .map(|project_times| project_times.as_slice())
+                    .unwrap_or_default()
+            });
+        let mut project_times = project_times.to_vec();
+        for (p, _) in previous_times.unwrap_or_default() {
+            if !project_times.iter().any(|(q, _)| q == p) {
+                project_times.push((p.clone(), Duration::new(0)));
+            }
+        }
+        let previous_duration = |p: &Project| {
+            previous_times
+                .unwrap_or_default()
+                .iter()
+                .find(|(q, _)| q == p)
+                .map(|(_, dur)| *dur)
+                .unwrap_or_default()
+        };
+        let billing_of = |p: &Project| {
+            annotations
+                .billing
+                .as_ref()
+                .and_then(|billings_by_user| billings_by_user.value.get(user))
+                .and_then(|project_billings| project_billings.iter().find(|(q, _)| q == p))
+                .map(|(_, billing)| billing.clone())
+                .unwrap_or_default()
+        };
+        let durations: Vec<Duration> = project_times.iter().map(|(_, dur)| *dur).collect();
+        let whole = self.sum_durations(durations.iter());
+        let delta = previous_times
+            .map(|previous_times| {
+                let previous: Vec<Duration> = previous_times.iter().map(|(_, dur)| *dur).collect();
+                self.format_delta(
+                    &self.summary_rounding.total(&durations),
+                    &self.summary_rounding.total(&previous),
+                )
+            })
+            .unwrap_or_default();
+        let project_times: Vec<&(Project, Duration)> = if self.summary_shares {
+            // the projects of the same hours are kept in order of name
+            project_times
+                .iter()
+                .sorted_by(|(_, dur1), (_, dur2)| dur2.value.cmp(&dur1.value))
+                .collect()
+        } else {
+            project_times.iter().collect()
+        };
+        UserContext {
+            name: user.to_string(),
+            total: self.format_summary_total(&durations),
+            delta,
+            projects: project_times
+                .into_iter()
+                .map(|(p, dur)| {
+                    let billing = billing_of(p);
+                    ProjectContext {
+                        name: p.to_string(),
+                        label: p.to_string(),
+                        time: self.summary_rounding.format(dur),
+                        bar: String::new(),
+                        duration: *dur,
+                        share: self.format_share(dur, &whole),
+                        delta: previous_times
+                            .map(|_| self.format_project_delta(dur, &previous_duration(p)))
+                            .unwrap_or_default(),
+                        running: annotations.running.contains(&(user.clone(), p.clone())),
+                        billable: self.format_billing_hours(&billing.billable),
+                        non_billable: self.format_billing_hours(&billing.non_billable),
+                        amount: billing.amount.to_string(),
+                        descriptions: descriptions_by_project
+                            .and_then(|by_project| by_project.get(p))
+                            .map(|descriptions| {
+                                descriptions
+                                    .iter()
+                                    .map(|(description, dur)| DescriptionContext {
+                                        name: description.to_string(),
+                                        time: self.summary_rounding.format(dur),
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Formats billable or non-billable hours, which are empty unless `billing`
+    fn format_billing_hours(&self, dur: &Duration) -> String {
+        if self.billing {
+            self.billing_rounding.format(dur)
+        } else {
+            String::new()
+        }
+    }
+
+    fn format_summary_total(&self, durations: &[Duration]) -> String {
+        self.summary_rounding
+            .format(&self.summary_rounding.total(durations))
+    }
+
+    fn sum_durations<'a>(&self, durations: impl Iterator<Item = &'a Duration>) -> Duration {
+        durations.fold(Duration::new(0), |acc, dur| acc + *dur)
+    }
+
+    /// Formats the change from the previous duration of a project, rounding both durations
+    fn format_project_delta(&self, current: &Duration, previous: &Duration) -> String {
+        self.format_delta(
+            &self.summary_rounding.round(current),
+            &self.summary_rounding.round(previous),
+        )
     }
 
-    fn get_project_message_title(&self, begin_date: &NaiveDate, end_date: &NaiveDate) -> String {
+    /// Formats the change from rounded `previous` to rounded `current` with an arrow and percentage
+    ///
+    /// e.g. "+3.5h ▲, +41%", "-2h ▼, -50%", "±0h" or "+1h ▲" if `previous` is 0
+    fn format_delta(&self, current: &Duration, previous: &Duration) -> String {
+        let (sign, arrow, diff) = match current.value.cmp(&previous.value) {
+            Ordering::Greater => ("+", " ▲", current.value - previous.value),
+            Ordering::Less => ("-", " ▼", previous.value - current.value),
+            Ordering::Equal => return "±0h".to_string(),
+        };
+        let delta = format!(
+            "{}{}h{}",
+            sign,
+            self.summary_rounding.format(&Duration::new(diff)),
+            arrow
+        );
+        if previous.value == 0 {
+            return delta;
+        }
         format!(
-            "*Toggl summary report* [{begin}-{end}]\n",
-            begin = begin_date.format("%Y/%m/%d"),
-            end = end_date.format("%Y/%m/%d"),
+            "{}, {}{}%",
+            delta,
+            sign,
+            (diff * 100 + previous.value / 2) / previous.value
         )
     }
 
-    fn get_project_message_user_entry(
+    /// Formats the share of exact `part` in `whole` as a rounded percentage, e.g. "45%"
+    fn format_share(&self, part: &Duration, whole: &Duration) -> String {
+        if whole.value == 0 {
+            return "0%".to_string();
+        }
+        format!("{}%", (part.value * 100 + whole.value / 2) / whole.value)
+    }
+
+    /// Formats days of too few hours and weeks over the maximum per user
+    ///
+    /// Users are mentioned by their Slack member IDs if `mention` is true.
+    pub fn get_alert_message(
         &self,
-        user: &User,
-        project_times: &Vec<(Project, Duration)>,
+        alerts_by_user: &[UserAlerts],
+        mention: bool,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
     ) -> String {
-        format!(
-            "\n*{name}*\n\n```{project_times_text}```",
-            name = user.to_string(),
-            project_times_text = project_times.iter().fold(String::new(), |acc, (p, dur)| {
-                acc + &format!(
-                    "{project}: {time}h\n",
-                    project = p.to_string(),
-                    time = self.format_duration_time(dur),
+        let labels = self.locale.labels();
+        let title = format!(
+            "*{title}* [{begin}-{end}]\n",
+            title = labels.alerts_title,
+            begin = self.locale.format_date(begin_date),
+            end = self.locale.format_date(end_date),
+        );
+        alerts_by_user.iter().fold(title, |acc, user_alerts| {
+            let mention = match &user_alerts.slack_id {
+                Some(slack_id) if mention => format!(" <@{}>", slack_id),
+                _ => String::new(),
+            };
+            let lines = user_alerts.alerts.iter().fold(String::new(), |acc, alert| {
+                acc + &self.get_alert_message_line(alert) + "\n"
+            });
+            acc + &format!(
+                "\n*{name}*{mention}\n\n```{lines}```",
+                name = user_alerts.user.to_string(),
+                mention = mention,
+                lines = lines,
+            )
+        })
+    }
+
+    fn get_alert_message_line(&self, alert: &LoggingAlert) -> String {
+        let labels = self.locale.labels();
+        let day = |date: &NaiveDate| {
+            format!(
+                "{} ({})",
+                self.locale.format_date(date),
+                self.locale.weekday(date.weekday())
+            )
+        };
+        match alert {
+            LoggingAlert::Missing { date, expected } => format!(
+                "{}: {} ({} {}h)",
+                day(date),
+                labels.no_entries,
+                labels.expected,
+                self.summary_rounding.format(expected)
+            ),
+            LoggingAlert::TooFew {
+                date,
+                logged,
+                expected,
+            } => format!(
+                "{}: {}h ({} {}h)",
+                day(date),
+                self.summary_rounding.format(logged),
+                labels.expected,
+                self.summary_rounding.format(expected)
+            ),
+            LoggingAlert::OverWeeklyMax { week, logged, max } => format!(
+                "{}-{}: {}h ({} {}h)",
+                self.locale.format_date(&week.first),
+                self.locale.format_date(&week.last),
+                self.summary_rounding.format(logged),
+                labels.weekly_max,
+                self.summary_rounding.format(max)
+            ),
+        }
+    }
+
+    /// Formats the consumed and remaining hours of project budgets with warnings over the thresholds
+    pub fn get_budget_message(
+        &self,
+        statuses: &[BudgetStatus],
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> String {
+        let labels = self.locale.labels();
+        let lines = statuses.iter().fold(String::new(), |acc, status| {
+            let name = if status.monthly {
+                format!("{} ({})", status.project, labels.monthly)
+            } else {
+                status.project.clone()
+            };
+            let rest = if status.consumed.value > status.budget.value {
+                format!(
+                    "{} {}h",
+                    labels.over_budget,
+                    self.summary_rounding
+                        .format(&Duration::new(status.consumed.value - status.budget.value))
                 )
-            })
+            } else {
+                format!(
+                    "{} {}h",
+                    labels.remaining,
+                    self.summary_rounding
+                        .format(&Duration::new(status.budget.value - status.consumed.value))
+                )
+            };
+            let exhaustion = status
+                .exhaustion
+                .map(|date| format!(", {} {}", labels.runs_out, self.locale.format_date(&date)))
+                .unwrap_or_default();
+            let warning = status
+                .threshold
+                .map(|threshold| format!(" ⚠ {}%", threshold))
+                .unwrap_or_default();
+            acc + &format!(
+                "{name}: {consumed}h / {budget}h ({percent}%), {rest}{exhaustion}{warning}\n",
+                name = name,
+                consumed = self.summary_rounding.format(&status.consumed),
+                budget = self.summary_rounding.format(&status.budget),
+                percent = status.percent,
+                rest = rest,
+                exhaustion = exhaustion,
+                warning = warning,
+            )
+        });
+        format!(
+            "*{title}* [{begin}-{end}]\n\n```{lines}```",
+            title = labels.budgets_title,
+            begin = self.locale.format_date(begin_date),
+            end = self.locale.format_date(end_date),
+            lines = lines,
         )
     }
 
@@ -62,74 +725,499 @@ impl MessageCreator {
         };
         begin_date
             .iter_days()
-            .take_while(|x| x <= &end_date)
+            .take_while(|x| x <= end_date)
             .collect()
     }
 
+    /// Returns the buckets of `csv_bucketing` between `begin_date` and `end_date`
+    ///
+    /// The first and the last buckets are partial if the period starts or ends in the middle of them.
+    fn get_sorted_buckets_in_period(
+        &self,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Vec<Bucket> {
+        self.get_sorted_dates_in_period(begin_date, end_date)
+            .into_iter()
+            .group_by(|d| self.csv_bucketing.first_day(d, begin_date))
+            .into_iter()
+            .map(|(first, dates)| Bucket {
+                first,
+                last: dates.last().unwrap_or(first),
+            })
+            .collect()
+    }
+
+    /// Omits the days off without time if `csv_hide_days_off` is set
+    ///
+    /// `logged` is the first days of buckets which have time.
+    fn retain_csv_buckets(
+        &self,
+        buckets: Vec<Bucket>,
+        logged: &BTreeSet<NaiveDate>,
+    ) -> Vec<Bucket> {
+        match &self.calendar {
+            Some(calendar) if self.csv_hide_days_off => buckets
+                .into_iter()
+                .filter(|b| {
+                    self.csv_bucketing.granularity != Granularity::Day
+                        || !calendar.is_day_off(&b.first)
+                        || logged.contains(&b.first)
+                })
+                .collect(),
+            _ => buckets,
+        }
+    }
+
     /// Returns report text csv-formatted
     ///
     /// e.g. (blanks are inserted for visibility here)
     /// Project,   User, 2020/12/01, 2020/12/02, 2020/12/03, ...
     /// projectA, Alice,  0.5,     1,     0, ...
     /// projectA,   Bob,    0,     5,     2, ...
+    ///
+    /// If `billing` is set, the billable and non-billable hours of `billings_by_user`
+    /// and the billed amount in each currency are appended to the rows.
     pub fn create_text_for_csv(
         &self,
-        dur_time_by_project_user_date: &Vec<(RecordKey, Duration)>,
+        dur_time_by_project_user_date: &[(RecordKey, Duration)],
+        billings_by_user: Option<&BillingRecords>,
         begin_date: &NaiveDate,
         end_date: &NaiveDate,
     ) -> String {
+        self.write_csv_text(self.get_detailed_table(
+            dur_time_by_project_user_date,
+            billings_by_user,
+            begin_date,
+            end_date,
+        ))
+    }
+
+    /// Returns the cells of the detailed CSV including the header, which are rendered into other formats as well
+    pub fn get_detailed_table(
+        &self,
+        dur_time_by_project_user_date: &[(RecordKey, Duration)],
+        billings_by_user: Option<&BillingRecords>,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Vec<Vec<String>> {
         let summed_dur_time_by_project_user_date =
-            self.sumup_durations(&dur_time_by_project_user_date);
-        let dates = self.get_sorted_dates_in_period(begin_date, end_date);
-        let projects: BTreeSet<Project> = summed_dur_time_by_project_user_date
+            self.sumup_durations_by_bucket(dur_time_by_project_user_date, begin_date);
+        let logged: BTreeSet<NaiveDate> = summed_dur_time_by_project_user_date
             .iter()
-            .map(|(k, _)| k.project.clone())
+            .filter(|(_, dur)| dur.value > 0)
+            .map(|(k, _)| k.date)
+            .collect();
+        let buckets = self.retain_csv_buckets(
+            self.get_sorted_buckets_in_period(begin_date, end_date),
+            &logged,
+        );
+        let projects: BTreeSet<Project> = summed_dur_time_by_project_user_date
+            .keys()
+            .map(|k| k.project.clone())
             .collect();
         let users: BTreeSet<User> = summed_dur_time_by_project_user_date
-            .iter()
-            .map(|(k, _)| k.user.clone())
+            .keys()
+            .map(|k| k.user.clone())
             .collect();
 
-        self.write_csv(
-            &users,
-            &projects,
-            &dates,
-            &summed_dur_time_by_project_user_date,
+        let currencies = self.get_csv_currencies(billings_by_user.into_iter());
+
+        self.arrange_csv_records(
+            [
+                vec![self.get_csv_header(&buckets, &currencies)],
+                self.get_csv_rows(
+                    &users,
+                    &projects,
+                    &buckets,
+                    &summed_dur_time_by_project_user_date,
+                    billings_by_user,
+                    &currencies,
+                ),
+            ]
+            .concat(),
         )
     }
 
-    fn write_csv(
+    /// Returns report text csv-formatted with the workspace column
+    ///
+    /// e.g. (blanks are inserted for visibility here)
+    /// Workspace,  Project,  User, 2020/12/01, 2020/12/02, ...
+    /// Company,   projectA, Alice,  0.5,     1, ...
+    /// Community, projectB, Alice,    0,     2, ...
+    pub fn create_text_for_csv_by_workspace(
         &self,
-        users: &BTreeSet<User>,
-        projects: &BTreeSet<Project>,
-        dates: &Vec<NaiveDate>,
-        dur_times_for_record_key: &HashMap<RecordKey, Duration>,
+        dur_time_by_workspace: &[(Workspace, Vec<(RecordKey, Duration)>)],
+        billings_by_workspace: Option<&HashMap<Workspace, BillingRecords>>,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
     ) -> String {
-        let dates_str: Vec<String> = dates
+        self.write_csv_text(self.get_detailed_table_by_workspace(
+            dur_time_by_workspace,
+            billings_by_workspace,
+            begin_date,
+            end_date,
+        ))
+    }
+
+    /// Returns the cells of the detailed CSV with the workspace column including the header
+    pub fn get_detailed_table_by_workspace(
+        &self,
+        dur_time_by_workspace: &[(Workspace, Vec<(RecordKey, Duration)>)],
+        billings_by_workspace: Option<&HashMap<Workspace, BillingRecords>>,
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Vec<Vec<String>> {
+        let summed_by_workspace: Vec<(&Workspace, HashMap<RecordKey, Duration>)> =
+            dur_time_by_workspace
+                .iter()
+                .map(|(w, dur_time_by_project_user_date)| {
+                    (
+                        w,
+                        self.sumup_durations_by_bucket(dur_time_by_project_user_date, begin_date),
+                    )
+                })
+                .collect();
+        let logged: BTreeSet<NaiveDate> = summed_by_workspace
             .iter()
-            .map(|d| d.format("%Y-%m-%d").to_string())
+            .flat_map(|(_, summed)| summed.iter())
+            .filter(|(_, dur)| dur.value > 0)
+            .map(|(k, _)| k.date)
             .collect();
+        let buckets = self.retain_csv_buckets(
+            self.get_sorted_buckets_in_period(begin_date, end_date),
+            &logged,
+        );
+        let workspace_label = match self.csv_layout {
+            CsvLayout::Long => "workspace",
+            _ => self.locale.labels().workspace,
+        };
+        let currencies = self.get_csv_currencies(
+            billings_by_workspace
+                .into_iter()
+                .flat_map(|billings| billings.values()),
+        );
+        let mut records = vec![[
+            vec![workspace_label.to_string()],
+            self.get_csv_header(&buckets, &currencies),
+        ]
+        .concat()];
+        for (w, summed) in summed_by_workspace {
+            let projects: BTreeSet<Project> = summed.keys().map(|k| k.project.clone()).collect();
+            let users: BTreeSet<User> = summed.keys().map(|k| k.user.clone()).collect();
+            let billings_by_user = billings_by_workspace.and_then(|billings| billings.get(w));
+            for row in self.get_csv_rows(
+                &users,
+                &projects,
+                &buckets,
+                &summed,
+                billings_by_user,
+                &currencies,
+            ) {
+                records.push([vec![w.to_string()], row].concat());
+            }
+        }
+        self.arrange_csv_records(records)
+    }
+
+    /// Returns the currencies billed in any of `billings`, which have an amount column each if `billing`
+    fn get_csv_currencies<'a>(
+        &self,
+        billings: impl Iterator<Item = &'a BillingRecords>,
+    ) -> BTreeSet<Currency> {
+        if !self.billing {
+            return BTreeSet::new();
+        }
+        billings
+            .flat_map(|billings_by_user| billings_by_user.value.values())
+            .flatten()
+            .flat_map(|(_, billing)| billing.amount.value.keys().cloned())
+            .collect()
+    }
+
+    /// Transposes the header and the rows if dates are shown as rows
+    fn arrange_csv_records(&self, records: Vec<Vec<String>>) -> Vec<Vec<String>> {
+        if self.csv_dates_as_rows && self.csv_layout != CsvLayout::Long {
+            let columns = records.first().map(|header| header.len()).unwrap_or(0);
+            (0..columns)
+                .map(|i| records.iter().map(|row| row[i].clone()).collect())
+                .collect()
+        } else {
+            records
+        }
+    }
 
+    /// Writes the cells of `get_detailed_table` as CSV text
+    pub fn write_csv_text(&self, records: Vec<Vec<String>>) -> String {
         let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        let header: Vec<String> = [
-            vec!["Project".to_string(), "User".to_string()],
-            dates_str.clone(),
-        ]
-        .concat();
-        wtr.write_record(header).expect("error");
+        for record in records {
+            wtr.write_record(record).expect("error");
+        }
+        String::from_utf8(wtr.into_inner().unwrap_or_default()).unwrap_or_default()
+    }
 
-        for p in projects.iter().sorted() {
-            for u in users.iter().sorted() {
-                let durations: Vec<String> = self
-                    .search_duration_time(&dates, &u, &p, &dur_times_for_record_key)
+    fn get_csv_header(&self, buckets: &[Bucket], currencies: &BTreeSet<Currency>) -> Vec<String> {
+        let labels = match self.csv_layout {
+            CsvLayout::ProjectUser => vec![self.locale.labels().project, self.locale.labels().user],
+            CsvLayout::UserProject => vec![self.locale.labels().user, self.locale.labels().project],
+            CsvLayout::Long => {
+                return ["date", "user", "project", "hours"]
                     .iter()
-                    .map(|d| self.format_duration_time(d))
+                    .map(|h| h.to_string())
+                    .collect()
+            }
+        };
+        let dates_str: Vec<String> = buckets
+            .iter()
+            .map(|b| {
+                let mut label = self.csv_bucketing.label(b);
+                if self.csv_bucketing.granularity != Granularity::Day {
+                    return label;
+                }
+                if self.csv_weekdays {
+                    label += &format!(" ({})", self.locale.weekday(b.first.weekday()));
+                }
+                if let Some(day_off) = self
+                    .calendar
+                    .as_ref()
+                    .and_then(|c| c.day_off_label(&b.first, self.locale.labels()))
+                {
+                    label += &format!(" [{}]", day_off);
+                }
+                label
+            })
+            .collect();
+        let total = if self.csv_totals.row {
+            vec![self.locale.labels().total.to_string()]
+        } else {
+            vec![]
+        };
+        let billing = if self.billing {
+            [
+                vec![
+                    self.locale.labels().billable_column.to_string(),
+                    self.locale.labels().non_billable_column.to_string(),
+                ],
+                currencies.iter().map(|c| c.to_string()).collect(),
+            ]
+            .concat()
+        } else {
+            vec![]
+        };
+        [
+            labels.iter().map(|l| l.to_string()).collect(),
+            dates_str,
+            total,
+            billing,
+        ]
+        .concat()
+    }
+
+    /// Returns a row for every pair of `projects` and `users` in the layout, followed by the enabled totals
+    ///
+    /// Rows whose durations are all 0 are omitted if `csv_sparse` is set.
+    fn get_csv_rows(
+        &self,
+        users: &BTreeSet<User>,
+        projects: &BTreeSet<Project>,
+        buckets: &[Bucket],
+        dur_times_for_record_key: &HashMap<RecordKey, Duration>,
+        billings_by_user: Option<&BillingRecords>,
+        currencies: &BTreeSet<Currency>,
+    ) -> Vec<Vec<String>> {
+        let dates: Vec<NaiveDate> = buckets.iter().map(|b| b.first).collect();
+        let billing_of = |u: &User, p: &Project| {
+            billings_by_user
+                .and_then(|billings| billings.value.get(u))
+                .and_then(|project_billings| project_billings.iter().find(|(q, _)| q == p))
+                .map(|(_, billing)| billing.clone())
+                .unwrap_or_default()
+        };
+        let mut durations_by_project_user: Vec<(&Project, &User, Vec<Duration>)> = projects
+            .iter()
+            .sorted()
+            .cartesian_product(users.iter().sorted())
+            .map(|(p, u)| {
+                let durations = self.search_duration_time(&dates, u, p, dur_times_for_record_key);
+                (p, u, durations)
+            })
+            .filter(|(_, _, durations)| !self.csv_sparse || durations.iter().any(|d| d.value > 0))
+            .collect();
+        match self.csv_layout {
+            CsvLayout::ProjectUser => {
+                let cells: Vec<CsvCells> = durations_by_project_user
+                    .into_iter()
+                    .map(|(p, u, durations)| {
+                        (p.to_string(), u.to_string(), durations, billing_of(u, p))
+                    })
+                    .collect();
+                self.get_csv_grouped_rows(
+                    cells,
+                    dates.len(),
+                    self.csv_totals.project,
+                    self.csv_totals.user,
+                    currencies,
+                )
+            }
+            CsvLayout::UserProject => {
+                durations_by_project_user
+                    .sort_by(|(p1, u1, _), (p2, u2, _)| (u1, p1).cmp(&(u2, p2)));
+                let cells: Vec<CsvCells> = durations_by_project_user
+                    .into_iter()
+                    .map(|(p, u, durations)| {
+                        (u.to_string(), p.to_string(), durations, billing_of(u, p))
+                    })
                     .collect();
-                let row: Vec<String> = [vec![p.to_string(), u.to_string()], durations].concat();
-                wtr.write_record(row).expect("error");
+                self.get_csv_grouped_rows(
+                    cells,
+                    dates.len(),
+                    self.csv_totals.user,
+                    self.csv_totals.project,
+                    currencies,
+                )
+            }
+            CsvLayout::Long => buckets
+                .iter()
+                .enumerate()
+                .flat_map(|(i, bucket)| {
+                    durations_by_project_user
+                        .iter()
+                        .sorted_by(|(p1, u1, _), (p2, u2, _)| (u1, p1).cmp(&(u2, p2)))
+                        .filter(move |(_, _, durations)| !self.csv_sparse || durations[i].value > 0)
+                        .map(move |(p, u, durations)| {
+                            vec![
+                                self.csv_bucketing.label(bucket),
+                                u.to_string(),
+                                p.to_string(),
+                                self.csv_rounding.format(&durations[i]),
+                            ]
+                        })
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns rows grouped by the first label, followed by the enabled totals
+    ///
+    /// `cells` must be sorted by the labels.
+    /// `group_totals` adds the total row of each group of the first label,
+    /// and `second_totals` adds the subtotal rows of each second label.
+    fn get_csv_grouped_rows(
+        &self,
+        cells: Vec<CsvCells>,
+        columns: usize,
+        group_totals: bool,
+        second_totals: bool,
+        currencies: &BTreeSet<Currency>,
+    ) -> Vec<Vec<String>> {
+        let sum_billings = |billings: Vec<&Billing>| {
+            billings
+                .into_iter()
+                .fold(Billing::default(), |acc, billing| acc + billing.clone())
+        };
+        let mut rows = vec![];
+        for (first, group) in &cells.iter().group_by(|(first, _, _, _)| first) {
+            let group: Vec<_> = group.collect();
+            for (_, second, durations, billing) in &group {
+                rows.push(self.get_csv_row(
+                    first.clone(),
+                    second.clone(),
+                    durations,
+                    billing,
+                    currencies,
+                ));
+            }
+            if group_totals {
+                let subtotals = self.sum_columns(group.iter().map(|(_, _, d, _)| d), columns);
+                rows.push(self.get_csv_row(
+                    first.clone(),
+                    self.locale.labels().total.to_string(),
+                    &subtotals,
+                    &sum_billings(group.iter().map(|(_, _, _, b)| b).collect()),
+                    currencies,
+                ));
             }
         }
-        String::from_utf8(wtr.into_inner().unwrap_or(vec![])).unwrap_or(String::from(""))
+        if second_totals {
+            let seconds: Vec<&String> = cells
+                .iter()
+                .map(|(_, second, _, _)| second)
+                .unique()
+                .collect();
+            // the order of the second labels in the first group is kept
+            for second in seconds {
+                let matched: Vec<&CsvCells> =
+                    cells.iter().filter(|(_, s, _, _)| s == second).collect();
+                let subtotals = self.sum_columns(matched.iter().map(|(_, _, d, _)| d), columns);
+                rows.push(self.get_csv_row(
+                    self.locale.labels().total.to_string(),
+                    second.clone(),
+                    &subtotals,
+                    &sum_billings(matched.iter().map(|(_, _, _, b)| b).collect()),
+                    currencies,
+                ));
+            }
+        }
+        if self.csv_totals.grand {
+            let totals = self.sum_columns(cells.iter().map(|(_, _, d, _)| d), columns);
+            rows.push(self.get_csv_row(
+                self.locale.labels().total.to_string(),
+                self.locale.labels().total.to_string(),
+                &totals,
+                &sum_billings(cells.iter().map(|(_, _, _, b)| b).collect()),
+                currencies,
+            ));
+        }
+        rows
+    }
+
+    fn get_csv_row(
+        &self,
+        first: String,
+        second: String,
+        durations: &[Duration],
+        billing: &Billing,
+        currencies: &BTreeSet<Currency>,
+    ) -> Vec<String> {
+        let mut row: Vec<String> = durations
+            .iter()
+            .map(|d| self.csv_rounding.format(d))
+            .collect();
+        if self.csv_totals.row {
+            row.push(
+                self.csv_rounding
+                    .format(&self.csv_rounding.total(durations)),
+            );
+        }
+        if self.billing {
+            row.push(self.billing_rounding.format(&billing.billable));
+            row.push(self.billing_rounding.format(&billing.non_billable));
+            row.extend(currencies.iter().map(|c| {
+                billing
+                    .amount
+                    .value
+                    .get(c)
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string()
+            }));
+        }
+        [vec![first, second], row].concat()
+    }
+
+    /// Sums up each column of `rows` by the rounding policy of CSV
+    fn sum_columns<'a>(
+        &self,
+        rows: impl Iterator<Item = &'a Vec<Duration>>,
+        columns: usize,
+    ) -> Vec<Duration> {
+        let rows: Vec<&Vec<Duration>> = rows.collect();
+        (0..columns)
+            .map(|i| {
+                let column: Vec<Duration> = rows.iter().map(|row| row[i]).collect();
+                self.csv_rounding.total(&column)
+            })
+            .collect()
     }
 
     /// Searches the duration time for the given `user` and `project` in all the date of `dates`
@@ -137,7 +1225,7 @@ impl MessageCreator {
     /// If is doesn't exists, 0 is returned for the date.
     fn search_duration_time(
         &self,
-        dates: &Vec<NaiveDate>,
+        dates: &[NaiveDate],
         user: &User,
         project: &Project,
         dur_times_for_record_key: &HashMap<RecordKey, Duration>,
@@ -157,34 +1245,32 @@ impl MessageCreator {
             .collect()
     }
 
-    /// Formats duration time in msec to human-readable string
-    ///
-    /// e.g. 3600_000(msec) -> "1"
-    fn format_duration_time(&self, dur: &Duration) -> String {
-        let msec = dur.value;
-        let minutes = msec / (1000 * 60);
-        let hours = minutes / 60;
-        let remainder = minutes % 60;
-        self.round_minutes_to_hour(hours, remainder).to_string()
-    }
-
-    /// round minutes(0-59) to hour(0.0, 0.5, 1.0)
-    fn round_minutes_to_hour(&self, hours: u64, minutes: u64) -> f64 {
-        let hours_f64 = hours as f64;
-        match minutes {
-            _ if minutes >= 45 => hours_f64 + 1.0,
-            _ if minutes >= 15 => hours_f64 + 0.5,
-            _ => hours_f64,
-        }
+    /// Sums up duration times per combination of `RecordKey`, whose date is the first day of the bucket
+    fn sumup_durations_by_bucket(
+        &self,
+        dur_time_by_project_user_date: &[(RecordKey, Duration)],
+        begin_date: &NaiveDate,
+    ) -> HashMap<RecordKey, Duration> {
+        let bucketed: Vec<(RecordKey, Duration)> = dur_time_by_project_user_date
+            .iter()
+            .map(|(k, dur)| {
+                let key = RecordKey {
+                    date: self.csv_bucketing.first_day(&k.date, begin_date),
+                    ..k.clone()
+                };
+                (key, *dur)
+            })
+            .collect();
+        self.sumup_durations(&bucketed)
     }
 
     /// Sums up duration times per combination of `RecordKey`
     fn sumup_durations(
         &self,
-        dur_time_by_project_user_date: &Vec<(RecordKey, Duration)>,
+        dur_time_by_project_user_date: &[(RecordKey, Duration)],
     ) -> HashMap<RecordKey, Duration> {
         dur_time_by_project_user_date
-            .into_iter()
+            .iter()
             .cloned()
             .into_group_map()
             .into_iter()
@@ -194,58 +1280,387 @@ impl MessageCreator {
 }
 
 #[cfg(test)]
+#[allow(clippy::inconsistent_digit_grouping)]
 mod tests {
     use super::*;
+    use crate::values::{Amount, Description, Money, Period};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn get_project_message_must_work_when_there_is_only_one_user() {
+        let mc = MessageCreator::default();
+
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let dur1 = Duration::new(3600_000);
+        let dur2 = Duration::new(7200_000);
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                user.clone(),
+                vec![(project1.clone(), dur1), (project2.clone(), dur2)],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let begin_date = NaiveDate::from_ymd(2020, 12, 1);
+        let end_date = NaiveDate::from_ymd(2020, 12, 31);
+
+        let actual = mc
+            .get_project_message(&project_times_by_user, &begin_date, &end_date)
+            .unwrap();
+        let expected = format!(
+            "{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/31]\n",
+            "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_must_return_text_ordered_by_user_and_project_when_there_are_two_users() {
+        let mc = MessageCreator::default();
+
+        let user1 = User::new("Alice");
+        let user2 = User::new("Bob");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let dur1 = Duration::new(3600_000);
+        let dur2 = Duration::new(7200_000);
+        let project_times_by_user = ProjectRecords::new(
+            [
+                (
+                    user2.clone(),
+                    vec![(project1.clone(), dur2), (project2.clone(), dur1)],
+                ),
+                (
+                    user1.clone(),
+                    vec![(project1.clone(), dur1), (project2.clone(), dur2)],
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let begin_date = NaiveDate::from_ymd(2020, 12, 1);
+        let end_date = NaiveDate::from_ymd(2020, 12, 31);
+
+        let actual = mc
+            .get_project_message(&project_times_by_user, &begin_date, &end_date)
+            .unwrap();
+        let expected = format!(
+            "{}{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/31]\n",
+            "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h\n```",
+            "\n*Bob*\n\n```ProjectA: 2h\nProjectB: 1h\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_must_render_the_given_template() {
+        let mc = MessageCreator {
+            summary_template: SummaryTemplate::new(
+                "custom.hbs",
+                "{{#each workspaces}}{{#each users}}{{name}} {{total}}h:{{#each projects}} {{name}}{{/each}}\n{{/each}}{{/each}}{{total}}h",
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let dur1 = Duration::new(3600_000);
+        let dur2 = Duration::new(7200_000);
+        let project_times_by_user = ProjectRecords::new(
+            [(user, vec![(project1, dur1), (project2, dur2)])]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+
+        let actual = mc
+            .get_project_message(&project_times_by_user, &date, &date)
+            .unwrap();
+        let expected = "Alice 3h: ProjectA ProjectB\n3h";
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_with_annotations_must_list_descriptions_under_each_project() {
+        let mc = MessageCreator::default();
+
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                user.clone(),
+                vec![
+                    (project1.clone(), Duration::new(3600_000)),
+                    (project2.clone(), Duration::new(7200_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let descriptions_by_user = DescriptionRecords::new(
+            [(
+                user.clone(),
+                [(
+                    project1.clone(),
+                    vec![
+                        (
+                            Description::new(None, "Fix login bug"),
+                            Duration::new(2700_000),
+                        ),
+                        (Description::new(Some("Review"), ""), Duration::new(900_000)),
+                    ],
+                )]
+                .iter()
+                .cloned()
+                .collect(),
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let begin_date = NaiveDate::from_ymd(2020, 12, 1);
+        let end_date = NaiveDate::from_ymd(2020, 12, 31);
+
+        let annotations = SummaryAnnotations {
+            descriptions: Some(descriptions_by_user),
+            ..Default::default()
+        };
+
+        let actual = mc
+            .get_project_message_with_annotations(
+                &project_times_by_user,
+                &annotations,
+                &begin_date,
+                &end_date,
+            )
+            .unwrap();
+        let expected = format!(
+            "{}{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/31]\n",
+            "\n*Alice*\n\n```ProjectA: 1h\n  Fix login bug: 1h\n  [Review] (no description): 0.5h\n",
+            "ProjectB: 2h\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_with_annotations_must_mark_running_projects() {
+        let mc = MessageCreator::default();
+
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                user.clone(),
+                vec![
+                    (project1.clone(), Duration::new(3600_000)),
+                    (project2.clone(), Duration::new(7200_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let annotations = SummaryAnnotations {
+            running: [(user.clone(), project2.clone())].iter().cloned().collect(),
+            ..Default::default()
+        };
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+
+        let actual = mc
+            .get_project_message_with_annotations(
+                &project_times_by_user,
+                &annotations,
+                &date,
+                &date,
+            )
+            .unwrap();
+        let expected = format!(
+            "{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/01]\n",
+            "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h (running)\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_with_annotations_must_show_billing_if_enabled() {
+        let mc = MessageCreator {
+            billing: true,
+            ..Default::default()
+        };
+
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                user.clone(),
+                vec![
+                    (project1.clone(), Duration::new(3600_000)),
+                    (project2.clone(), Duration::new(7200_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let billing1 = Billing::new(
+            Duration::new(3600_000),
+            Duration::new(0),
+            Money::single(Currency::new("USD"), Amount::new(1250)),
+        );
+        let billing2 = Billing::new(Duration::new(0), Duration::new(7200_000), Money::default());
+        let annotations = SummaryAnnotations {
+            billing: Some(BillingRecords::new(
+                [(user, vec![(project1, billing1), (project2, billing2)])]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            )),
+            ..Default::default()
+        };
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+
+        let actual = mc
+            .get_project_message_with_annotations(
+                &project_times_by_user,
+                &annotations,
+                &date,
+                &date,
+            )
+            .unwrap();
+        let expected = format!(
+            "{}{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/01]\n",
+            "\n*Alice*\n\n```ProjectA: 1h [billable 1h (12.50 USD), non-billable 0h]\n",
+            "ProjectB: 2h [billable 0h, non-billable 2h]\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn check_options_must_reject_billing_in_long_layout() {
+        let mc = MessageCreator {
+            billing: true,
+            csv_layout: CsvLayout::Long,
+            ..Default::default()
+        };
+
+        assert!(mc.check_options().is_err())
+    }
 
     #[test]
-    fn get_project_message_must_work_when_there_is_only_one_user() {
-        let mc = MessageCreator {};
+    fn check_options_must_reject_totals_in_long_layout() {
+        let mc = MessageCreator {
+            csv_totals: CsvTotals {
+                row: true,
+                ..Default::default()
+            },
+            csv_layout: CsvLayout::Long,
+            ..Default::default()
+        };
 
-        let user = User::new("Alice");
-        let project1 = Project::new(Some("ProjectA"));
-        let project2 = Project::new(Some("ProjectB"));
-        let dur1 = Duration::new(3600_000);
-        let dur2 = Duration::new(7200_000);
-        let project_times_by_user = ProjectRecords::new(
-            [(
-                user.clone(),
-                vec![(project1.clone(), dur1), (project2.clone(), dur2)],
-            )]
-            .iter()
-            .cloned()
-            .collect(),
-        );
+        assert!(mc.check_options().is_err())
+    }
+
+    #[test]
+    fn get_project_message_by_workspace_must_group_users_under_each_workspace() {
+        let mc = MessageCreator::default();
+
+        let records = |user: &str, project: &str| {
+            ProjectRecords::new(
+                [(
+                    User::new(user),
+                    vec![(Project::new(Some(project)), Duration::new(3600_000))],
+                )]
+                .iter()
+                .cloned()
+                .collect(),
+            )
+        };
+        let reports = vec![
+            (
+                Workspace::new("Company"),
+                records("Alice", "ProjectA"),
+                SummaryAnnotations::default(),
+            ),
+            (
+                Workspace::new("Community"),
+                records("Alice", "ProjectB"),
+                SummaryAnnotations::default(),
+            ),
+        ];
         let begin_date = NaiveDate::from_ymd(2020, 12, 1);
         let end_date = NaiveDate::from_ymd(2020, 12, 31);
 
-        let actual = mc.get_project_message(&project_times_by_user, &begin_date, &end_date);
+        let actual = mc
+            .get_project_message_by_workspace(&reports, &begin_date, &end_date)
+            .unwrap();
         let expected = format!(
-            "{}{}",
+            "{}{}{}{}{}",
             "*Toggl summary report* [2020/12/01-2020/12/31]\n",
-            "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h\n```",
+            "\n*[Company]*\n",
+            "\n*Alice*\n\n```ProjectA: 1h\n```",
+            "\n*[Community]*\n",
+            "\n*Alice*\n\n```ProjectB: 1h\n```",
         );
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn get_project_message_must_return_text_ordered_by_user_and_project_when_there_are_two_users() {
-        let mc = MessageCreator {};
+    fn get_project_message_must_return_only_title_text_when_there_are_no_users() {
+        let mc = MessageCreator::default();
+
+        let begin_date = NaiveDate::from_ymd(2020, 12, 1);
+        let end_date = NaiveDate::from_ymd(2021, 12, 1);
+
+        let actual = mc
+            .get_project_message(
+                &ProjectRecords::new(BTreeMap::new()),
+                &begin_date,
+                &end_date,
+            )
+            .unwrap();
+        let expected = "*Toggl summary report* [2020/12/01-2021/12/01]\n";
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_must_show_shares_and_ranking_if_enabled() {
+        let mc = MessageCreator {
+            summary_shares: true,
+            ..Default::default()
+        };
 
-        let user1 = User::new("Alice");
-        let user2 = User::new("Bob");
         let project1 = Project::new(Some("ProjectA"));
         let project2 = Project::new(Some("ProjectB"));
-        let dur1 = Duration::new(3600_000);
-        let dur2 = Duration::new(7200_000);
         let project_times_by_user = ProjectRecords::new(
             [
                 (
-                    user2.clone(),
-                    vec![(project1.clone(), dur2), (project2.clone(), dur1)],
+                    User::new("Alice"),
+                    vec![
+                        (project1.clone(), Duration::new(3600_000)),
+                        (project2.clone(), Duration::new(7200_000)),
+                    ],
                 ),
                 (
-                    user1.clone(),
-                    vec![(project1.clone(), dur1), (project2.clone(), dur2)],
+                    User::new("Bob"),
+                    vec![(project1.clone(), Duration::new(3600_000))],
                 ),
             ]
             .iter()
@@ -255,49 +1670,152 @@ mod tests {
         let begin_date = NaiveDate::from_ymd(2020, 12, 1);
         let end_date = NaiveDate::from_ymd(2020, 12, 31);
 
-        let actual = mc.get_project_message(&project_times_by_user, &begin_date, &end_date);
+        let actual = mc
+            .get_project_message(&project_times_by_user, &begin_date, &end_date)
+            .unwrap();
         let expected = format!(
-            "{}{}{}",
+            "{}{}{}{}",
             "*Toggl summary report* [2020/12/01-2020/12/31]\n",
-            "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h\n```",
-            "\n*Bob*\n\n```ProjectA: 2h\nProjectB: 1h\n```",
+            "\n*Alice*\n\n```ProjectB: 2h (67%)\nProjectA: 1h (33%)\nTotal: 3h\n```",
+            "\n*Bob*\n\n```ProjectA: 1h (100%)\nTotal: 1h\n```",
+            "\n*Projects by hours*\n\n```1. ProjectA: 2h (50%)\n2. ProjectB: 2h (50%)\nTotal: 4h\n```",
         );
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn get_project_message_user_entry_must_return_text_for_the_given_user() {
-        let mc = MessageCreator {};
+    fn get_project_message_with_annotations_must_compare_with_the_previous_period() {
+        let mc = MessageCreator::default();
 
-        let user = User::new("Alice");
         let project1 = Project::new(Some("ProjectA"));
         let project2 = Project::new(Some("ProjectB"));
-        let dur1 = Duration::new(3600_000);
-        let dur2 = Duration::new(7200_000);
-        let project_times = vec![(project1.clone(), dur1), (project2.clone(), dur2)];
+        let project3 = Project::new(Some("ProjectC"));
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![
+                    (project1.clone(), Duration::new(43_200_000)),
+                    (project2.clone(), Duration::new(3_600_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let previous = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![
+                    (project1.clone(), Duration::new(30_600_000)),
+                    (project3.clone(), Duration::new(7_200_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let annotations = SummaryAnnotations {
+            previous: Some(previous),
+            ..Default::default()
+        };
+        let begin_date = NaiveDate::from_ymd(2020, 12, 7);
+        let end_date = NaiveDate::from_ymd(2020, 12, 13);
+
+        let actual = mc
+            .get_project_message_with_annotations(
+                &project_times_by_user,
+                &annotations,
+                &begin_date,
+                &end_date,
+            )
+            .unwrap();
+        let expected = format!(
+            "{}{}{}{}",
+            "*Toggl summary report* [2020/12/07-2020/12/13]\n",
+            "\n*Alice* 13h (+2.5h ▲, +24%)\n\n",
+            "```ProjectA: 12h (+3.5h ▲, +41%)\nProjectB: 1h (+1h ▲)\nProjectC: 0h (-2h ▼, -100%)\n```",
+            "\n*Projects by hours*\n\n```1. ProjectA: 12h (+3.5h ▲, +41%)\n2. ProjectB: 1h (+1h ▲)\n3. ProjectC: 0h (-2h ▼, -100%)\nTotal: 13h (+2.5h ▲, +24%)\n```",
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn get_project_message_must_draw_aligned_bars_if_enabled() {
+        let mc = MessageCreator {
+            summary_chart: Some(BarChart { width: 4 }),
+            ..Default::default()
+        };
 
-        let actual = mc.get_project_message_user_entry(&user, &project_times);
-        let expected = "\n*Alice*\n\n```ProjectA: 1h\nProjectB: 2h\n```";
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![
+                    (Project::new(Some("開発")), Duration::new(14400_000)),
+                    (Project::new(Some("Review")), Duration::new(5400_000)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let date = NaiveDate::from_ymd(2020, 12, 1);
 
+        let actual = mc
+            .get_project_message(&project_times_by_user, &date, &date)
+            .unwrap();
+        let expected = format!(
+            "{}{}{}",
+            "*Toggl summary report* [2020/12/01-2020/12/01]\n",
+            "\n*Alice*\n\n```開発   ████ 4h\nReview █▌   1.5h\n```",
+            "\n*Projects by hours*\n\n```開発   ████ 4h\nReview █▌   1.5h\nTotal: 5.5h\n```",
+        );
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn get_project_message_title_must_return_title_text() {
-        let mc = MessageCreator {};
+    fn get_project_message_must_be_translated_by_the_locale() {
+        let mc = MessageCreator {
+            locale: Locale::Ja,
+            ..Default::default()
+        };
 
+        let user = User::new("Alice");
+        let project = Project::new(Some("ProjectA"));
+        let project_times_by_user = ProjectRecords::new(
+            [(
+                user.clone(),
+                vec![(project.clone(), Duration::new(5400_000))],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let annotations = SummaryAnnotations {
+            running: [(user, project)].iter().cloned().collect(),
+            ..Default::default()
+        };
         let begin_date = NaiveDate::from_ymd(2020, 12, 1);
-        let end_date = NaiveDate::from_ymd(2021, 12, 1);
-
-        let actual = mc.get_project_message_title(&begin_date, &end_date);
-        let expected = "*Toggl summary report* [2020/12/01-2021/12/01]\n";
+        let end_date = NaiveDate::from_ymd(2020, 12, 31);
 
+        let actual = mc
+            .get_project_message_with_annotations(
+                &project_times_by_user,
+                &annotations,
+                &begin_date,
+                &end_date,
+            )
+            .unwrap();
+        let expected = format!(
+            "{}{}",
+            "*Toggl 集計レポート* [2020年12月1日-2020年12月31日]\n",
+            "\n*Alice*\n\n```ProjectA: 1.5h (計測中)\n```",
+        );
         assert_eq!(actual, expected)
     }
 
     #[test]
     fn get_sorted_dates_in_period_must_return_dates_in_the_given_period() {
-        let mc = MessageCreator {};
+        let mc = MessageCreator::default();
 
         let begin = NaiveDate::from_ymd(2019, 12, 29);
         let end = NaiveDate::from_ymd(2020, 1, 2);
@@ -316,7 +1834,7 @@ mod tests {
     #[test]
     #[should_panic]
     fn get_sorted_dates_in_period_must_panic_if_end_is_prior_to_begin() {
-        let mc = MessageCreator {};
+        let mc = MessageCreator::default();
 
         let begin = NaiveDate::from_ymd(2020, 12, 1);
         let end = NaiveDate::from_ymd(2020, 11, 30);
@@ -325,9 +1843,86 @@ mod tests {
     }
 
     #[test]
-    fn write_csv_must_return_() {
-        let mc = MessageCreator {};
+    fn get_sorted_buckets_in_period_must_clip_partial_weeks_to_the_period() {
+        let begin = NaiveDate::from_ymd(2020, 12, 2);
+        let end = NaiveDate::from_ymd(2020, 12, 15);
+        let buckets = |week_start: Weekday| {
+            let mc = MessageCreator {
+                csv_bucketing: Bucketing {
+                    granularity: Granularity::Week,
+                    week_start,
+                },
+                ..Default::default()
+            };
+            mc.get_sorted_buckets_in_period(&begin, &end)
+                .iter()
+                .map(|b| (b.first.day(), b.last.day(), mc.csv_bucketing.label(b)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            buckets(Weekday::Mon),
+            vec![
+                (2, 6, "2020-W49".to_string()),
+                (7, 13, "2020-W50".to_string()),
+                (14, 15, "2020-W51".to_string())
+            ]
+        );
+        assert_eq!(
+            buckets(Weekday::Sun),
+            vec![
+                (2, 5, "2020-11-29".to_string()),
+                (6, 12, "2020-12-06".to_string()),
+                (13, 15, "2020-12-13".to_string())
+            ]
+        );
+    }
 
+    #[test]
+    fn create_text_for_csv_must_sum_up_durations_by_month() {
+        let mc = MessageCreator {
+            csv_bucketing: Bucketing {
+                granularity: Granularity::Month,
+                week_start: Weekday::Mon,
+            },
+            ..Default::default()
+        };
+        let record = |month: u32, day: u32| {
+            (
+                RecordKey {
+                    user: User::new("Alice"),
+                    project: Project::new(Some("ProjectA")),
+                    date: NaiveDate::from_ymd(2020, month, day),
+                },
+                Duration::new(3600_000),
+            )
+        };
+
+        let actual = mc.create_text_for_csv(
+            &[
+                record(11, 20),
+                record(11, 30),
+                record(12, 1),
+                record(12, 31),
+            ],
+            None,
+            &NaiveDate::from_ymd(2020, 11, 20),
+            &NaiveDate::from_ymd(2020, 12, 31),
+        );
+        let expected = format!(
+            "{}\n{}\n",
+            "Project,User,2020-11,2020-12", "ProjectA,Alice,2,2",
+        );
+
+        assert_eq!(actual, expected)
+    }
+
+    fn write_csv_fixture() -> (
+        BTreeSet<User>,
+        BTreeSet<Project>,
+        Vec<Bucket>,
+        HashMap<RecordKey, Duration>,
+    ) {
         let user1 = User::new("Alice");
         let user2 = User::new("Bob");
         let users: BTreeSet<User> = [user1.clone(), user2.clone()].iter().cloned().collect();
@@ -341,7 +1936,7 @@ mod tests {
 
         let date1 = NaiveDate::from_ymd(2020, 12, 1);
         let date2 = NaiveDate::from_ymd(2020, 12, 2);
-        let dates = vec![date1, date2];
+        let buckets = MessageCreator::default().get_sorted_buckets_in_period(&date1, &date2);
         let record_key1 = RecordKey {
             user: user1.clone(),
             project: project1.clone(),
@@ -359,8 +1954,39 @@ mod tests {
                 .iter()
                 .cloned()
                 .collect();
+        (users, projects, buckets, dur_times_for_record_key)
+    }
+
+    /// Returns the header and the rows of the CSV without billing
+    fn csv_records(
+        mc: &MessageCreator,
+        users: &BTreeSet<User>,
+        projects: &BTreeSet<Project>,
+        buckets: &[Bucket],
+        dur_times_for_record_key: &HashMap<RecordKey, Duration>,
+    ) -> Vec<Vec<String>> {
+        let currencies = BTreeSet::new();
+        [
+            vec![mc.get_csv_header(buckets, &currencies)],
+            mc.get_csv_rows(
+                users,
+                projects,
+                buckets,
+                dur_times_for_record_key,
+                None,
+                &currencies,
+            ),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn write_csv_must_return_() {
+        let mc = MessageCreator::default();
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let actual = mc.write_csv(&users, &projects, &dates, &dur_times_for_record_key);
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
         let expected = format!(
             "{}\n{}\n{}\n{}\n{}\n",
             "Project,User,2020-12-01,2020-12-02",
@@ -374,150 +2000,353 @@ mod tests {
     }
 
     #[test]
-    fn search_duration_time_must_return_the_corresponding_duration_time_when_it_exists() {
-        let mc = MessageCreator {};
-
-        let user = User::new("Alice");
-        let project = Project::new(Some("Project"));
-        let date = NaiveDate::from_ymd(2020, 12, 1);
-        let dates = vec![date];
-        let record_key = RecordKey {
-            user: user.clone(),
-            project: project.clone(),
-            date: date,
+    fn write_csv_must_return_all_totals_when_enabled() {
+        let mc = MessageCreator {
+            csv_totals: "all".parse().unwrap(),
+            ..Default::default()
         };
-        let dur = Duration::new(100);
-        let dur_times_for_record_key: HashMap<RecordKey, Duration> =
-            [(record_key, dur)].iter().cloned().collect();
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let actual = mc.search_duration_time(&dates, &user, &project, &dur_times_for_record_key);
-        let expected = vec![dur];
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            "Project,User,2020-12-01,2020-12-02,Total",
+            "ProjectA,Alice,1,0,1",
+            "ProjectA,Bob,0,0,0",
+            "ProjectA,Total,1,0,1",
+            "ProjectB,Alice,0,0,0",
+            "ProjectB,Bob,0,1,1",
+            "ProjectB,Total,0,1,1",
+            "Total,Alice,1,0,1",
+            "Total,Bob,0,1,1",
+            "Total,Total,1,1,2",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn search_duration_time_must_return_0_when_it_does_not_exist() {
-        let mc = MessageCreator {};
+    fn write_csv_must_return_only_enabled_totals() {
+        let mc = MessageCreator {
+            csv_totals: "user,grand".parse().unwrap(),
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let user = User::new("Alice");
-        let project = Project::new(Some("Project"));
-        let date = NaiveDate::from_ymd(2020, 12, 1);
-        let dates = vec![date];
-        let dur_times_for_record_key: HashMap<RecordKey, Duration> = HashMap::new();
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            "Project,User,2020-12-01,2020-12-02",
+            "ProjectA,Alice,1,0",
+            "ProjectA,Bob,0,0",
+            "ProjectB,Alice,0,0",
+            "ProjectB,Bob,0,1",
+            "Total,Alice,1,0",
+            "Total,Bob,0,1",
+            "Total,Total,1,1",
+        );
 
-        let actual = mc.search_duration_time(&dates, &user, &project, &dur_times_for_record_key);
-        let expected = vec![Duration::new(0)];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn write_csv_must_translate_the_header_with_weekdays() {
+        let mc = MessageCreator {
+            locale: Locale::Ja,
+            csv_weekdays: true,
+            csv_totals: "row".parse().unwrap(),
+            csv_sparse: true,
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
+
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n",
+            "プロジェクト,ユーザー,2020-12-01 (火),2020-12-02 (水),合計",
+            "ProjectA,Alice,1,0,1",
+            "ProjectB,Bob,0,1,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn format_duration_time_must_14min_to_0() {
-        let mc = MessageCreator {};
+    fn create_text_for_csv_must_mark_days_off_and_hide_those_without_time() {
+        let mut calendar = Calendar::default();
+        calendar.holidays.insert(
+            NaiveDate::from_ymd(2020, 12, 31),
+            "New Year's Eve".to_string(),
+        );
+        let mc = MessageCreator {
+            csv_weekdays: true,
+            calendar: Some(calendar),
+            csv_hide_days_off: true,
+            ..Default::default()
+        };
+        let record = |day: u32| {
+            (
+                RecordKey {
+                    user: User::new("Alice"),
+                    project: Project::new(Some("ProjectA")),
+                    date: NaiveDate::from_ymd(2020, 12, day),
+                },
+                Duration::new(3_600_000),
+            )
+        };
 
-        let input = Duration::new(14 * 60 * 1000);
-        let actual = mc.format_duration_time(&input);
-        let expected = "0".to_string();
+        // Saturday 26th and Thursday 31st are shown because of the time on them
+        let actual = mc.create_text_for_csv(
+            &[record(26), record(28), record(31)],
+            None,
+            &NaiveDate::from_ymd(2020, 12, 26),
+            &NaiveDate::from_ymd(2020, 12, 31),
+        );
+        let expected = format!(
+            "{}{}\n{}\n",
+            "Project,User,2020-12-26 (Sat) [weekend],2020-12-28 (Mon),2020-12-29 (Tue),",
+            "2020-12-30 (Wed),2020-12-31 (Thu) [New Year's Eve]",
+            "ProjectA,Alice,1,1,0,0,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn format_duration_time_must_15min_to_05() {
-        let mc = MessageCreator {};
+    fn write_csv_must_drop_all_zero_rows_if_sparse() {
+        let mc = MessageCreator {
+            csv_sparse: true,
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let input = Duration::new(15 * 60 * 1000);
-        let actual = mc.format_duration_time(&input);
-        let expected = "0.5".to_string();
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n",
+            "Project,User,2020-12-01,2020-12-02", "ProjectA,Alice,1,0", "ProjectB,Bob,0,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn format_duration_time_must_44min_to_05() {
-        let mc = MessageCreator {};
+    fn write_csv_must_group_by_user_in_user_project_layout() {
+        let mc = MessageCreator {
+            csv_layout: CsvLayout::UserProject,
+            csv_totals: "user,project".parse().unwrap(),
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let input = Duration::new(44 * 60 * 1000);
-        let actual = mc.format_duration_time(&input);
-        let expected = "0.5".to_string();
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+            "User,Project,2020-12-01,2020-12-02",
+            "Alice,ProjectA,1,0",
+            "Alice,ProjectB,0,0",
+            "Alice,Total,1,0",
+            "Bob,ProjectA,0,0",
+            "Bob,ProjectB,0,1",
+            "Bob,Total,0,1",
+            "Total,ProjectA,1,0",
+            "Total,ProjectB,0,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn format_duration_time_must_45min_to_05() {
-        let mc = MessageCreator {};
+    fn write_csv_must_show_dates_as_rows_if_chosen() {
+        let mc = MessageCreator {
+            csv_dates_as_rows: true,
+            csv_sparse: true,
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let input = Duration::new(45 * 60 * 1000);
-        let actual = mc.format_duration_time(&input);
-        let expected = "1".to_string();
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n{}\n",
+            "Project,ProjectA,ProjectB", "User,Alice,Bob", "2020-12-01,1,0", "2020-12-02,0,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn format_duration_time_must_60min_to_1() {
-        let mc = MessageCreator {};
+    fn write_csv_must_return_one_row_per_value_in_long_layout() {
+        let mc = MessageCreator {
+            csv_layout: CsvLayout::Long,
+            csv_sparse: true,
+            ..Default::default()
+        };
+        let (users, projects, buckets, dur_times_for_record_key) = write_csv_fixture();
 
-        let input = Duration::new(60 * 60 * 1000);
-        let actual = mc.format_duration_time(&input);
-        let expected = "1".to_string();
+        let records = csv_records(&mc, &users, &projects, &buckets, &dur_times_for_record_key);
+        let actual = mc.write_csv_text(mc.arrange_csv_records(records));
+        let expected = format!(
+            "{}\n{}\n{}\n",
+            "date,user,project,hours", "2020-12-01,Alice,ProjectA,1", "2020-12-02,Bob,ProjectB,1",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn round_minutes_to_hour_must_1h14min_to_1hour() {
-        let mc = MessageCreator {};
+    fn create_text_for_csv_by_workspace_must_prepend_workspace_column() {
+        let mc = MessageCreator::default();
 
-        let hour = 1;
-        let minutes = 14;
-        let actual = mc.round_minutes_to_hour(hour, minutes);
-        let expected = 1.0;
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        let records = |project: &str| {
+            vec![(
+                RecordKey {
+                    user: User::new("Alice"),
+                    project: Project::new(Some(project)),
+                    date,
+                },
+                Duration::new(3600_000),
+            )]
+        };
+        let dur_time_by_workspace = vec![
+            (Workspace::new("Company"), records("ProjectA")),
+            (Workspace::new("Community"), records("ProjectB")),
+        ];
 
+        let actual =
+            mc.create_text_for_csv_by_workspace(&dur_time_by_workspace, None, &date, &date);
+        let expected = format!(
+            "{}\n{}\n{}\n",
+            "Workspace,Project,User,2020-12-01",
+            "Company,ProjectA,Alice,1",
+            "Community,ProjectB,Alice,1",
+        );
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn round_minutes_to_hour_must_1h15min_to_1_5hour() {
-        let mc = MessageCreator {};
+    fn create_text_for_csv_must_append_billing_columns_if_enabled() {
+        let mc = MessageCreator {
+            billing: true,
+            csv_totals: CsvTotals {
+                grand: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let alice = User::new("Alice");
+        let bob = User::new("Bob");
+        let project = Project::new(Some("ProjectA"));
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        let record = |user: &User| {
+            (
+                RecordKey {
+                    user: user.clone(),
+                    project: project.clone(),
+                    date,
+                },
+                Duration::new(3600_000),
+            )
+        };
+        let billing = |billable: u64, amount: Money| {
+            Billing::new(
+                Duration::new(billable),
+                Duration::new(3600_000 - billable),
+                amount,
+            )
+        };
+        let billings = BillingRecords::new(
+            [
+                (
+                    alice.clone(),
+                    vec![(
+                        project.clone(),
+                        billing(
+                            3600_000,
+                            Money::single(Currency::new("USD"), Amount::new(1250)),
+                        ),
+                    )],
+                ),
+                (
+                    bob.clone(),
+                    vec![(
+                        project.clone(),
+                        billing(
+                            1800_000,
+                            Money::single(Currency::new("EUR"), Amount::new(500)),
+                        ),
+                    )],
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
 
-        let hour = 1;
-        let minutes = 15;
-        let actual = mc.round_minutes_to_hour(hour, minutes);
-        let expected = 1.5;
+        let actual = mc.create_text_for_csv(
+            &[record(&alice), record(&bob)],
+            Some(&billings),
+            &date,
+            &date,
+        );
+        let expected = format!(
+            "{}\n{}\n{}\n{}\n",
+            "Project,User,2020-12-01,Billable,NonBillable,EUR,USD",
+            "ProjectA,Alice,1,1,0,0.00,12.50",
+            "ProjectA,Bob,1,0.5,0.5,5.00,0.00",
+            "Total,Total,2,1.5,0.5,5.00,12.50",
+        );
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn round_minutes_to_hour_must_1h44min_to_1_5hour() {
-        let mc = MessageCreator {};
+    fn search_duration_time_must_return_the_corresponding_duration_time_when_it_exists() {
+        let mc = MessageCreator::default();
+
+        let user = User::new("Alice");
+        let project = Project::new(Some("Project"));
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        let dates = vec![date];
+        let record_key = RecordKey {
+            user: user.clone(),
+            project: project.clone(),
+            date,
+        };
+        let dur = Duration::new(100);
+        let dur_times_for_record_key: HashMap<RecordKey, Duration> =
+            [(record_key, dur)].iter().cloned().collect();
 
-        let hour = 1;
-        let minutes = 44;
-        let actual = mc.round_minutes_to_hour(hour, minutes);
-        let expected = 1.5;
+        let actual = mc.search_duration_time(&dates, &user, &project, &dur_times_for_record_key);
+        let expected = vec![dur];
 
         assert_eq!(actual, expected)
     }
 
     #[test]
-    fn round_minutes_to_hour_must_1h45min_to_1_5hour() {
-        let mc = MessageCreator {};
+    fn search_duration_time_must_return_0_when_it_does_not_exist() {
+        let mc = MessageCreator::default();
 
-        let hour = 1;
-        let minutes = 45;
-        let actual = mc.round_minutes_to_hour(hour, minutes);
-        let expected = 2.0;
+        let user = User::new("Alice");
+        let project = Project::new(Some("Project"));
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        let dates = vec![date];
+        let dur_times_for_record_key: HashMap<RecordKey, Duration> = HashMap::new();
+
+        let actual = mc.search_duration_time(&dates, &user, &project, &dur_times_for_record_key);
+        let expected = vec![Duration::new(0)];
 
         assert_eq!(actual, expected)
     }
 
     #[test]
     fn sumup_durations_must_sum_up_2_elements_which_have_the_same_record_key() {
-        let mc = MessageCreator {};
+        let mc = MessageCreator::default();
 
         let record_key = RecordKey {
             user: User::new("Alice"),
@@ -538,7 +2367,7 @@ mod tests {
 
     #[test]
     fn sumup_durations_must_not_sum_up_2_elements_which_have_different_record_keys() {
-        let mc = MessageCreator {};
+        let mc = MessageCreator::default();
 
         let record_key1 = RecordKey {
             user: User::new("Alice"),
@@ -561,4 +2390,87 @@ mod tests {
             .collect::<HashMap<_, _>>();
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn get_alert_message_must_list_alerts_and_mention_users_if_enabled() {
+        let mc = MessageCreator::default();
+        let alerts_by_user = vec![UserAlerts {
+            user: User::new("Alice"),
+            slack_id: Some("U012AB3CD".to_string()),
+            alerts: vec![
+                LoggingAlert::Missing {
+                    date: NaiveDate::from_ymd(2020, 12, 7),
+                    expected: Duration::new(28_800_000),
+                },
+                LoggingAlert::TooFew {
+                    date: NaiveDate::from_ymd(2020, 12, 8),
+                    logged: Duration::new(10_800_000),
+                    expected: Duration::new(28_800_000),
+                },
+                LoggingAlert::OverWeeklyMax {
+                    week: Bucket {
+                        first: NaiveDate::from_ymd(2020, 12, 7),
+                        last: NaiveDate::from_ymd(2020, 12, 13),
+                    },
+                    logged: Duration::new(162_000_000),
+                    max: Duration::new(144_000_000),
+                },
+            ],
+        }];
+        let begin_date = NaiveDate::from_ymd(2020, 12, 7);
+        let end_date = NaiveDate::from_ymd(2020, 12, 13);
+
+        let actual = mc.get_alert_message(&alerts_by_user, true, &begin_date, &end_date);
+        let expected = format!(
+            "{}{}{}{}{}",
+            "*Time logging alerts* [2020/12/07-2020/12/13]\n",
+            "\n*Alice* <@U012AB3CD>\n\n```",
+            "2020/12/07 (Mon): no entries (expected 8h)\n",
+            "2020/12/08 (Tue): 3h (expected 8h)\n",
+            "2020/12/07-2020/12/13: 45h (weekly max 40h)\n```",
+        );
+        assert_eq!(actual, expected);
+
+        let actual = mc.get_alert_message(&alerts_by_user, false, &begin_date, &end_date);
+        assert!(actual.contains("\n*Alice*\n"), "{}", actual);
+    }
+
+    #[test]
+    fn get_budget_message_must_warn_budgets_over_the_thresholds() {
+        let mc = MessageCreator::default();
+        let begin_date = NaiveDate::from_ymd(2020, 12, 1);
+        let end_date = NaiveDate::from_ymd(2020, 12, 10);
+        let hours = |h: u64| Duration::new(h * 3_600_000);
+        let statuses = vec![
+            BudgetStatus {
+                project: "ProjectA".to_string(),
+                monthly: false,
+                period: Period::new(NaiveDate::from_ymd(2020, 11, 1), end_date),
+                budget: hours(100),
+                consumed: hours(80),
+                percent: 80,
+                exhaustion: Some(NaiveDate::from_ymd(2020, 12, 20)),
+                threshold: Some(80),
+            },
+            BudgetStatus {
+                project: "ProjectB".to_string(),
+                monthly: true,
+                period: Period::new(begin_date, end_date),
+                budget: hours(20),
+                consumed: hours(22),
+                percent: 110,
+                exhaustion: None,
+                threshold: Some(100),
+            },
+        ];
+
+        let actual = mc.get_budget_message(&statuses, &begin_date, &end_date);
+        let expected = format!(
+            "{}{}{}",
+            "*Project budgets* [2020/12/01-2020/12/10]\n\n```",
+            "ProjectA: 80h / 100h (80%), remaining 20h, runs out 2020/12/20 ⚠ 80%\n",
+            "ProjectB (monthly): 22h / 20h (110%), over 2h ⚠ 100%\n```",
+        );
+        assert_eq!(actual, expected);
+    }
 }
@@ -0,0 +1,434 @@
+use crate::cache::fnv1a;
+use crate::toggl::RecordKey;
+use crate::values::{Duration, Project, User};
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use chrono::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    const WHITE: Rgb = Rgb([255, 255, 255]);
+    const BLACK: Rgb = Rgb([0, 0, 0]);
+    const GRAY: Rgb = Rgb([160, 160, 160]);
+
+    /// Parses a colour like "#06aaf5"
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+    }
+
+    /// e.g. "#06aaf5"
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0[0], self.0[1], self.0[2])
+    }
+
+    /// Returns a colour whose hue is derived from `name`, which is the same across runs
+    pub fn from_name(name: &str) -> Self {
+        let hue = (fnv1a(name) % 360) as f64;
+        // HSV with saturation 0.6 and value 0.85
+        let (s, v) = (0.6, 0.85);
+        let c = v * s;
+        let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+        let (r, g, b) = match (hue / 60.0) as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |value: f64| ((value + v - c) * 255.0).round() as u8;
+        Rgb([channel(r), channel(g), channel(b)])
+    }
+}
+
+/// Colours of projects, which are taken from Toggl where available
+#[derive(Clone, Debug, Default)]
+pub struct ProjectColors {
+    by_name: HashMap<String, Rgb>,
+}
+
+impl ProjectColors {
+    /// Sets the colour of the project unless it is already set, ignoring invalid colours
+    pub fn insert(&mut self, name: &str, hex: &str) {
+        if let Some(color) = Rgb::from_hex(hex) {
+            self.by_name.entry(name.to_string()).or_insert(color);
+        }
+    }
+
+    /// Returns the colour of `project`, which is gray for entries without project
+    pub fn get(&self, project: &Project) -> Rgb {
+        match &project.value {
+            None => Rgb::GRAY,
+            Some(p) => self
+                .by_name
+                .get(&p.value)
+                .copied()
+                .unwrap_or_else(|| Rgb::from_name(&p.value)),
+        }
+    }
+}
+
+/// RGB image drawn in memory
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: Rgb::WHITE.0.repeat((width * height) as usize),
+        }
+    }
+
+    /// Blends `color` at the pixel by `alpha` from 0 to 1
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f32) {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 3) as usize;
+        let alpha = alpha.max(0.0).min(1.0);
+        for (pixel, channel) in self.pixels[i..i + 3].iter_mut().zip(color.0.iter()) {
+            *pixel =
+                (f32::from(*pixel) * (1.0 - alpha) + f32::from(*channel) * alpha).round() as u8;
+        }
+    }
+
+    fn fill_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: Rgb) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    /// Fills the circle with slices of the fractions, clockwise from 12 o'clock
+    fn fill_pie(&mut self, cx: i64, cy: i64, radius: i64, slices: &[(f64, Rgb)]) {
+        for py in cy - radius..=cy + radius {
+            for px in cx - radius..=cx + radius {
+                let (dx, dy) = ((px - cx) as f64, (py - cy) as f64);
+                if dx * dx + dy * dy > (radius * radius) as f64 {
+                    continue;
+                }
+                let mut angle = dx.atan2(-dy) / (2.0 * std::f64::consts::PI);
+                if angle < 0.0 {
+                    angle += 1.0;
+                }
+                let mut edge = 0.0;
+                for (fraction, color) in slices {
+                    edge += fraction;
+                    if angle <= edge {
+                        self.blend(px, py, *color, 1.0);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws `text` whose top-left corner is at (`x`, `y`)
+    fn draw_text(&mut self, font: &FontVec, text: &str, x: f32, y: f32, size: f32) {
+        let scaled = font.as_scaled(PxScale::from(size));
+        let mut caret = x;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            let glyph = id.with_scale_and_position(size, point(caret, y + scaled.ascent()));
+            caret += scaled.h_advance(id);
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    self.blend(
+                        bounds.min.x as i64 + i64::from(gx),
+                        bounds.min.y as i64 + i64::from(gy),
+                        Rgb::BLACK,
+                        coverage,
+                    )
+                });
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&self.pixels)?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Renders charts of detailed records to PNG
+///
+/// Texts are drawn only if `font` is given.
+#[derive(Default)]
+pub struct ChartRenderer {
+    pub colors: ProjectColors,
+    pub font: Option<FontVec>,
+}
+
+impl ChartRenderer {
+    const TEXT_SIZE: f32 = 14.0;
+    const MARGIN: i64 = 10;
+    const LABEL_WIDTH: i64 = 120;
+    const LEGEND_WIDTH: i64 = 200;
+    const ROW_HEIGHT: i64 = 100;
+    const DAY_WIDTH: i64 = 24;
+    const BAR_WIDTH: i64 = 16;
+    const PIE_RADIUS: i64 = 140;
+    /// Fonts tried if no font is given, which include CJK glyphs where available
+    const FONT_CANDIDATES: [&'static str; 4] = [
+        "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    ];
+
+    /// Loads a TrueType or OpenType font, taking the first font of collections
+    pub fn load_font(path: &Path) -> Result<FontVec, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        FontVec::try_from_vec(bytes).map_err(|e| format!("Invalid font {}: {}", path.display(), e))
+    }
+
+    /// Returns the first installed font of the candidates
+    pub fn default_font() -> Option<FontVec> {
+        Self::FONT_CANDIDATES
+            .iter()
+            .find_map(|path| Self::load_font(Path::new(path)).ok())
+    }
+
+    /// Draws hours per user per day, stacked by project
+    ///
+    /// Each user has a row of bars, which share the same scale.
+    pub fn stacked_bars(
+        &self,
+        records: &[(RecordKey, Duration)],
+        begin_date: &NaiveDate,
+        end_date: &NaiveDate,
+    ) -> Result<Vec<u8>, String> {
+        let dates: Vec<NaiveDate> = begin_date
+            .iter_days()
+            .take_while(|d| d <= end_date)
+            .collect();
+        let mut durations: BTreeMap<(&User, NaiveDate), BTreeMap<&Project, u64>> = BTreeMap::new();
+        for (key, dur) in records {
+            *durations
+                .entry((&key.user, key.date))
+                .or_default()
+                .entry(&key.project)
+                .or_default() += dur.value;
+        }
+        let users: BTreeSet<&User> = records.iter().map(|(k, _)| &k.user).collect();
+        let projects: BTreeSet<&Project> = records.iter().map(|(k, _)| &k.project).collect();
+        let max = durations
+            .values()
+            .map(|by_project| by_project.values().sum::<u64>())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        // the longest bar leaves a space of 10px under the row above
+        let scale = |dur: u64| (dur as i64 * (Self::ROW_HEIGHT - 10)) / max as i64;
+
+        let plot_x = Self::MARGIN + Self::LABEL_WIDTH;
+        let plot_width = dates.len() as i64 * Self::DAY_WIDTH;
+        let axis_y = Self::MARGIN + users.len() as i64 * Self::ROW_HEIGHT;
+        let legend_height = projects.len() as i64 * 20;
+        let width = plot_x + plot_width + Self::MARGIN * 2 + Self::LEGEND_WIDTH;
+        let height = std::cmp::max(axis_y + 30, Self::MARGIN * 2 + legend_height);
+        let mut canvas = Canvas::new(width as u32, height as u32);
+
+        for (row, user) in users.iter().enumerate() {
+            let baseline = Self::MARGIN + (row as i64 + 1) * Self::ROW_HEIGHT;
+            canvas.fill_rect(plot_x, baseline, plot_width, 1, Rgb::GRAY);
+            if let Some(font) = &self.font {
+                let y = baseline - Self::ROW_HEIGHT / 2;
+                canvas.draw_text(
+                    font,
+                    &user.to_string(),
+                    Self::MARGIN as f32,
+                    y as f32,
+                    Self::TEXT_SIZE,
+                );
+            }
+            for (i, date) in dates.iter().enumerate() {
+                let x =
+                    plot_x + i as i64 * Self::DAY_WIDTH + (Self::DAY_WIDTH - Self::BAR_WIDTH) / 2;
+                if let Some(by_project) = durations.get(&(*user, *date)) {
+                    let mut stacked = 0;
+                    for (project, dur) in by_project {
+                        let bottom = baseline - scale(stacked);
+                        stacked += dur;
+                        let top = baseline - scale(stacked);
+                        canvas.fill_rect(
+                            x,
+                            top,
+                            Self::BAR_WIDTH,
+                            bottom - top,
+                            self.colors.get(project),
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(font) = &self.font {
+            // labels every few days not to overlap
+            let step = (48 / Self::DAY_WIDTH + 1) as usize;
+            for (i, date) in dates.iter().enumerate().step_by(step) {
+                let x = plot_x + i as i64 * Self::DAY_WIDTH;
+                canvas.draw_text(
+                    font,
+                    &date.format("%m/%d").to_string(),
+                    x as f32,
+                    (axis_y + 6) as f32,
+                    Self::TEXT_SIZE,
+                );
+            }
+            let hours = format!("max {:.1}h", max as f64 / 3_600_000.0);
+            canvas.draw_text(
+                font,
+                &hours,
+                Self::MARGIN as f32,
+                (axis_y + 6) as f32,
+                Self::TEXT_SIZE,
+            );
+        }
+        self.draw_legend(
+            &mut canvas,
+            plot_x + plot_width + Self::MARGIN * 2,
+            projects.iter().map(|p| (*p, p.to_string())),
+        );
+        canvas.encode().map_err(|e| e.to_string())
+    }
+
+    /// Draws the share of each project in the total hours
+    pub fn pie(&self, records: &[(RecordKey, Duration)]) -> Result<Vec<u8>, String> {
+        let mut durations: BTreeMap<&Project, u64> = BTreeMap::new();
+        for (key, dur) in records {
+            *durations.entry(&key.project).or_default() += dur.value;
+        }
+        let total: u64 = durations.values().sum();
+        let mut shares: Vec<(&Project, u64)> = durations.into_iter().collect();
+        // the projects of the same hours are kept in order of name
+        shares.sort_by(|(_, dur1), (_, dur2)| dur2.cmp(dur1));
+
+        let diameter = Self::PIE_RADIUS * 2;
+        let width = Self::MARGIN * 3 + diameter + Self::LEGEND_WIDTH;
+        let height = std::cmp::max(
+            Self::MARGIN * 2 + diameter,
+            Self::MARGIN * 2 + shares.len() as i64 * 20,
+        );
+        let mut canvas = Canvas::new(width as u32, height as u32);
+        let slices: Vec<(f64, Rgb)> = shares
+            .iter()
+            .map(|(p, dur)| (*dur as f64 / total.max(1) as f64, self.colors.get(p)))
+            .collect();
+        canvas.fill_pie(
+            Self::MARGIN + Self::PIE_RADIUS,
+            Self::MARGIN + Self::PIE_RADIUS,
+            Self::PIE_RADIUS,
+            &slices,
+        );
+        self.draw_legend(
+            &mut canvas,
+            Self::MARGIN * 2 + diameter,
+            shares.iter().map(|(p, dur)| {
+                let percent = (dur * 100 + total / 2) / total.max(1);
+                (*p, format!("{} {}%", p.to_string(), percent))
+            }),
+        );
+        canvas.encode().map_err(|e| e.to_string())
+    }
+
+    fn draw_legend<'a>(
+        &self,
+        canvas: &mut Canvas,
+        x: i64,
+        items: impl Iterator<Item = (&'a Project, String)>,
+    ) {
+        for (i, (project, text)) in items.enumerate() {
+            let y = Self::MARGIN + i as i64 * 20;
+            canvas.fill_rect(x, y + 2, 12, 12, self.colors.get(project));
+            if let Some(font) = &self.font {
+                canvas.draw_text(font, &text, (x + 18) as f32, y as f32, Self::TEXT_SIZE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(png_bytes: &[u8], x: u32, y: u32) -> [u8; 3] {
+        let decoder = png::Decoder::new(png_bytes);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        let i = ((y * info.width + x) * 3) as usize;
+        [buf[i], buf[i + 1], buf[i + 2]]
+    }
+
+    #[test]
+    fn colors_must_prefer_toggl_colors_and_be_deterministic_otherwise() {
+        let mut colors = ProjectColors::default();
+        colors.insert("ProjectA", "#06aaf5");
+        colors.insert("ProjectA", "#ffffff");
+        colors.insert("ProjectB", "invalid");
+
+        assert_eq!(
+            colors.get(&Project::new(Some("ProjectA"))),
+            Rgb([6, 170, 245])
+        );
+        assert_eq!(
+            colors.get(&Project::new(Some("ProjectB"))),
+            Rgb::from_name("ProjectB")
+        );
+        assert_eq!(colors.get(&Project::new::<String>(None)), Rgb::GRAY);
+    }
+
+    #[test]
+    fn stacked_bars_must_stack_projects_of_each_day() {
+        let mut colors = ProjectColors::default();
+        colors.insert("ProjectA", "#ff0000");
+        colors.insert("ProjectB", "#0000ff");
+        let renderer = ChartRenderer { colors, font: None };
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        let record = |project: &str| {
+            (
+                RecordKey {
+                    user: User::new("Alice"),
+                    project: Project::new(Some(project)),
+                    date,
+                },
+                Duration::new(3_600_000),
+            )
+        };
+
+        let png_bytes = renderer
+            .stacked_bars(&[record("ProjectA"), record("ProjectB")], &date, &date)
+            .unwrap();
+        let x = (ChartRenderer::MARGIN + ChartRenderer::LABEL_WIDTH + ChartRenderer::DAY_WIDTH / 2)
+            as u32;
+        let baseline = (ChartRenderer::MARGIN + ChartRenderer::ROW_HEIGHT) as u32;
+
+        assert_eq!(pixel(&png_bytes, x, baseline - 5), [255, 0, 0]);
+        assert_eq!(pixel(&png_bytes, x, baseline - 85), [0, 0, 255]);
+        assert_eq!(pixel(&png_bytes, x, baseline - 95), [255, 255, 255]);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::toggl::{TogglAccessor, TogglDetail, TogglDetailResponse, TogglSummaryResponse};
+use crate::values::{Duration, Project, ProjectRecords, User};
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One row of the detailed report exported from Toggl as CSV
+#[derive(Deserialize, Debug)]
+struct TogglCsvRecord {
+    #[serde(rename = "User")]
+    user: String,
+    #[serde(rename = "Client", default)]
+    client: String,
+    #[serde(rename = "Project", default)]
+    project: String,
+    #[serde(rename = "Task", default)]
+    task: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Billable", default)]
+    billable: String,
+    #[serde(rename = "Start date")]
+    start_date: NaiveDate,
+    #[serde(rename = "Start time")]
+    start_time: NaiveTime,
+    /// `hh:mm:ss`, where hours may exceed 24
+    #[serde(rename = "Duration")]
+    duration: String,
+}
+
+/// Reports API responses saved by `fetch --save` or exported from Toggl
+///
+/// These are rendered without accessing Toggl API.
+#[derive(Debug, Default)]
+pub struct SavedReport {
+    pub summaries: Vec<TogglSummaryResponse>,
+    pub details: Vec<TogglDetail>,
+}
+
+impl SavedReport {
+    /// Loads JSON and CSV files, looking into directories recursively
+    ///
+    /// The start times in CSV files are regarded as in `timezone`, or in the local timezone if None.
+    pub fn load(
+        paths: &[PathBuf],
+        timezone: Option<&Tz>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut saved = SavedReport::default();
+        for path in paths {
+            saved.add_path(path, timezone)?;
+        }
+        Ok(saved)
+    }
+
+    fn add_path(
+        &mut self,
+        path: &Path,
+        timezone: Option<&Tz>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<Vec<PathBuf>>>()?;
+            entries.sort();
+            for entry in entries {
+                self.add_path(&entry, timezone)?;
+            }
+            return Ok(());
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => self.add_json(&fs::read_to_string(path)?),
+            Some("csv") => {
+                let details = Self::parse_csv(fs::File::open(path)?, timezone)?;
+                self.details.extend(details);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a summary response, a page of detailed report or the entries cached per day
+    fn add_json(&mut self, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let value: serde_json::Value = serde_json::from_str(body)?;
+        if value.is_array() {
+            self.details
+                .extend(serde_json::from_value::<Vec<TogglDetail>>(value)?);
+        } else if value.get("total_count").is_some() {
+            self.details
+                .extend(serde_json::from_value::<TogglDetailResponse>(value)?.data);
+        } else {
+            self.summaries
+                .push(serde_json::from_value::<TogglSummaryResponse>(value)?);
+        }
+        Ok(())
+    }
+
+    /// Parses the detailed report exported from Toggl as CSV
+    pub fn parse_csv<R: io::Read>(
+        reader: R,
+        timezone: Option<&Tz>,
+    ) -> Result<Vec<TogglDetail>, Box<dyn std::error::Error>> {
+        let mut rdr = csv::Reader::from_reader(reader);
+        let headers = rdr.headers()?.clone();
+        // e.g. "Amount (USD)"
+        let amount_column = headers.iter().position(|h| h.starts_with("Amount ("));
+        let currency = amount_column.map(|i| {
+            headers[i]
+                .trim_start_matches("Amount (")
+                .trim_end_matches(')')
+                .to_string()
+        });
+        let mut details = vec![];
+        for record in rdr.records() {
+            let record = record?;
+            let row: TogglCsvRecord = record.deserialize(Some(&headers))?;
+            let start = Self::localize(row.start_date.and_time(row.start_time), timezone)
+                .ok_or_else(|| {
+                    format!(
+                        "Nonexistent start time: {} {}",
+                        row.start_date, row.start_time
+                    )
+                })?;
+            let amount = amount_column
+                .and_then(|i| record.get(i))
+                .filter(|a| !a.is_empty())
+                .map(|a| a.parse::<f64>())
+                .transpose()?;
+            let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+            details.push(TogglDetail {
+                description: row.description,
+                task: non_empty(row.task),
+                start,
+                dur: Self::parse_duration(&row.duration)?,
+                user: User::new(row.user),
+                project: Project::new(non_empty(row.project)),
+                uid: None,
+                pid: None,
+                client: non_empty(row.client),
+                is_billable: row.billable == "Yes",
+                billable: amount,
+                cur: amount.and(currency.clone()),
+                running: false,
+            });
+        }
+        Ok(details)
+    }
+
+    fn localize(datetime: NaiveDateTime, timezone: Option<&Tz>) -> Option<DateTime<FixedOffset>> {
+        match timezone {
+            Some(tz) => tz
+                .from_local_datetime(&datetime)
+                .earliest()
+                .map(|dt| dt.with_timezone(&dt.offset().fix())),
+            None => Local
+                .from_local_datetime(&datetime)
+                .earliest()
+                .map(|dt| dt.with_timezone(&dt.offset().fix())),
+        }
+    }
+
+    fn parse_duration(hms: &str) -> Result<Duration, String> {
+        let parts = hms
+            .split(':')
+            .map(|part| part.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|_| format!("Invalid duration: {}", hms))?;
+        match parts.as_slice() {
+            [h, m, s] => Ok(Duration::new(((h * 60 + m) * 60 + s) * 1000)),
+            _ => Err(format!("Invalid duration: {}", hms)),
+        }
+    }
+
+    /// Sums up the saved summaries
+    pub fn summary(&self) -> ProjectRecords {
+        let mut summary = ProjectRecords::new(BTreeMap::new());
+        for res in &self.summaries {
+            for (u, project_times) in TogglAccessor::convert_summary_to_hashmap(res).value {
+                for (p, dur) in project_times {
+                    summary.add(u.clone(), p, dur);
+                }
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::inconsistent_digit_grouping)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_must_read_toggl_detailed_export() {
+        let csv = "\
+User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount (USD)
+Alice,alice@example.com,ClientX,ProjectA,,Review,Yes,2020-12-01,09:00:00,2020-12-01,10:30:00,01:30:00,,15.00
+Bob,bob@example.com,,,,,No,2020-12-01,23:30:00,2020-12-02,00:30:00,01:00:00,,
+";
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+
+        let actual = SavedReport::parse_csv(csv.as_bytes(), Some(&tz)).unwrap();
+        assert_eq!(actual.len(), 2);
+        assert_eq!(
+            actual[0].start,
+            DateTime::parse_from_rfc3339("2020-12-01T09:00:00+09:00").unwrap()
+        );
+        assert_eq!(actual[0].dur, Duration::new(90 * 60_000));
+        assert_eq!(actual[0].project, Project::new(Some("ProjectA")));
+        assert_eq!(actual[0].client, Some("ClientX".to_string()));
+        assert_eq!(actual[0].task, None);
+        assert!(actual[0].is_billable);
+        assert_eq!(actual[0].billable, Some(15.0));
+        assert_eq!(actual[0].cur, Some("USD".to_string()));
+        assert_eq!(actual[1].project, Project::new::<String>(None));
+        assert!(!actual[1].is_billable);
+        assert_eq!(actual[1].cur, None);
+    }
+
+    #[test]
+    fn add_json_must_tell_summaries_from_details() {
+        let entry = r#"{"description": "Review", "start": "2020-12-01T09:00:00+09:00", "dur": 3600000, "user": "Alice", "project": "ProjectA"}"#;
+        let summary = r#"{"data": [{"id": 1, "title": {"user": "Alice"}, "items": [{"title": {"project": "ProjectA"}, "time": 3600000}]}]}"#;
+        let mut saved = SavedReport::default();
+
+        saved.add_json(&format!("[{}]", entry)).unwrap();
+        saved
+            .add_json(&format!(
+                r#"{{"total_count": 1, "per_page": 50, "data": [{}]}}"#,
+                entry
+            ))
+            .unwrap();
+        saved.add_json(summary).unwrap();
+
+        assert_eq!(saved.details.len(), 2);
+        let expected = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![(Project::new(Some("ProjectA")), Duration::new(3600_000))],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(saved.summary(), expected)
+    }
+}
@@ -0,0 +1,243 @@
+use crate::values::Duration;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundingMode {
+    /// Rounds half up
+    Nearest,
+    Up,
+    Down,
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(RoundingMode::Nearest),
+            "up" => Ok(RoundingMode::Up),
+            "down" => Ok(RoundingMode::Down),
+            _ => Err(format!("Unknown rounding mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DurationStyle {
+    /// Decimal hours, e.g. "1.5"
+    Decimal,
+    /// Hours and minutes, e.g. "1:30"
+    HourMinute,
+}
+
+impl FromStr for DurationStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "decimal" => Ok(DurationStyle::Decimal),
+            "hmm" | "h:mm" => Ok(DurationStyle::HourMinute),
+            _ => Err(format!("Unknown duration style: {}", s)),
+        }
+    }
+}
+
+/// How totals of several durations are computed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TotalsMode {
+    /// Sums up the exact durations and rounds the sum
+    RoundSum,
+    /// Sums up the rounded durations, which agrees with the sum of the shown values
+    SumRounded,
+}
+
+impl FromStr for TotalsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round_sum" => Ok(TotalsMode::RoundSum),
+            "sum_rounded" => Ok(TotalsMode::SumRounded),
+            _ => Err(format!("Unknown totals mode: {}", s)),
+        }
+    }
+}
+
+/// Rounding and format of durations in a message
+///
+/// The default rounds to the nearest half hour, showing decimal hours like "1.5".
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RoundingPolicy {
+    pub mode: RoundingMode,
+    /// Rounding increment in minutes, where 0 means exact durations
+    pub increment: u64,
+    pub style: DurationStyle,
+    /// Maximum decimal places of `DurationStyle::Decimal`
+    pub decimal_places: usize,
+    pub totals: TotalsMode,
+    /// Decimal separator of `DurationStyle::Decimal`, which is given by the locale
+    pub decimal_separator: char,
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        RoundingPolicy {
+            mode: RoundingMode::Nearest,
+            increment: 30,
+            style: DurationStyle::Decimal,
+            decimal_places: 2,
+            totals: TotalsMode::RoundSum,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl RoundingPolicy {
+    /// Overrides the settings by comma-separated `key=value`s
+    ///
+    /// e.g. "mode=up,increment=6,style=hmm,places=1,totals=sum_rounded"
+    pub fn with_spec(&self, spec: &str) -> Result<Self, String> {
+        let mut policy = *self;
+        for setting in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (key, value) = match setting.find('=') {
+                Some(i) => (&setting[..i], &setting[i + 1..]),
+                None => return Err(format!("Rounding setting must be key=value: {}", setting)),
+            };
+            match key {
+                "mode" => policy.mode = value.parse()?,
+                "increment" => {
+                    policy.increment = value
+                        .parse()
+                        .map_err(|_| format!("Invalid rounding increment: {}", value))?
+                }
+                "style" => policy.style = value.parse()?,
+                "places" => {
+                    policy.decimal_places = value
+                        .parse()
+                        .map_err(|_| format!("Invalid decimal places: {}", value))?
+                }
+                "totals" => policy.totals = value.parse()?,
+                _ => return Err(format!("Unknown rounding setting: {}", key)),
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Rounds `dur` to a multiple of the increment
+    pub fn round(&self, dur: &Duration) -> Duration {
+        let increment = self.increment * 60 * 1000;
+        if increment == 0 {
+            return *dur;
+        }
+        let units = match self.mode {
+            RoundingMode::Nearest => (dur.value + increment / 2) / increment,
+            RoundingMode::Up => (dur.value + increment - 1) / increment,
+            RoundingMode::Down => dur.value / increment,
+        };
+        Duration::new(units * increment)
+    }
+
+    /// Returns the total of `durs`, which is rounded by `format` as well
+    pub fn total(&self, durs: &[Duration]) -> Duration {
+        durs.iter()
+            .map(|dur| match self.totals {
+                TotalsMode::RoundSum => *dur,
+                TotalsMode::SumRounded => self.round(dur),
+            })
+            .fold(Duration::new(0), |acc, dur| acc + dur)
+    }
+
+    /// Formats rounded `dur` in hours
+    ///
+    /// e.g. 5400_000(msec) -> "1.5" or "1:30"
+    pub fn format(&self, dur: &Duration) -> String {
+        let rounded = self.round(dur);
+        match self.style {
+            DurationStyle::Decimal => {
+                let hours = format!(
+                    "{:.places$}",
+                    rounded.value as f64 / 3_600_000.0,
+                    places = self.decimal_places
+                );
+                let hours = if hours.contains('.') {
+                    hours.trim_end_matches('0').trim_end_matches('.')
+                } else {
+                    &hours
+                };
+                hours.replace('.', &self.decimal_separator.to_string())
+            }
+            DurationStyle::HourMinute => {
+                let minutes = (rounded.value + 30 * 1000) / (60 * 1000);
+                format!("{}:{:02}", minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(m: u64) -> Duration {
+        Duration::new(m * 60 * 1000)
+    }
+
+    #[test]
+    fn default_format_must_round_to_nearest_half_hour() {
+        let policy = RoundingPolicy::default();
+
+        assert_eq!(policy.format(&minutes(14)), "0");
+        assert_eq!(policy.format(&minutes(15)), "0.5");
+        assert_eq!(policy.format(&minutes(44)), "0.5");
+        assert_eq!(policy.format(&minutes(45)), "1");
+        assert_eq!(policy.format(&minutes(60)), "1");
+        assert_eq!(policy.format(&minutes(74)), "1");
+        assert_eq!(policy.format(&minutes(75)), "1.5");
+        assert_eq!(policy.format(&minutes(104)), "1.5");
+        assert_eq!(policy.format(&minutes(105)), "2");
+    }
+
+    #[test]
+    fn format_must_round_up_to_6_minutes() {
+        let policy = RoundingPolicy::default()
+            .with_spec("mode=up,increment=6")
+            .unwrap();
+
+        assert_eq!(policy.format(&Duration::new(1000)), "0.1");
+        assert_eq!(policy.format(&minutes(6)), "0.1");
+        assert_eq!(policy.format(&minutes(7)), "0.2");
+    }
+
+    #[test]
+    fn format_must_show_exact_hours_and_minutes() {
+        let decimal = RoundingPolicy::default()
+            .with_spec("increment=0,places=3")
+            .unwrap();
+        let hmm = RoundingPolicy::default()
+            .with_spec("mode=down,increment=1,style=hmm")
+            .unwrap();
+        let dur = Duration::new((100 * 60 + 59) * 1000);
+
+        assert_eq!(decimal.format(&dur), "1.683");
+        assert_eq!(hmm.format(&dur), "1:40");
+    }
+
+    #[test]
+    fn total_must_sum_up_rounded_durations_if_chosen() {
+        let round_sum = RoundingPolicy::default();
+        let sum_rounded = round_sum.with_spec("totals=sum_rounded").unwrap();
+        let durs = vec![minutes(20), minutes(20)];
+
+        assert_eq!(round_sum.format(&round_sum.total(&durs)), "0.5");
+        assert_eq!(sum_rounded.format(&sum_rounded.total(&durs)), "1");
+    }
+
+    #[test]
+    fn with_spec_must_reject_unknown_settings() {
+        let policy = RoundingPolicy::default();
+
+        assert!(policy.with_spec("mode=sideways").is_err());
+        assert!(policy.with_spec("color=red").is_err());
+        assert!(policy.with_spec("increment").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Debug)]
@@ -18,6 +19,7 @@ pub struct SlackAccessor {
 }
 impl SlackAccessor {
     const URL_POST_MESSAGE: &'static str = "https://slack.com/api/chat.postMessage";
+    const URL_FILES_UPLOAD: &'static str = "https://slack.com/api/files.upload";
 
     pub async fn send_message(
         &self,
@@ -45,8 +47,47 @@ impl SlackAccessor {
             .json::<SlackResponse>()
             .await?;
         match res.ok {
-            true => Ok(println!("Success")),
-            false => Ok(println!("Error: {:#?}", res.error)),
+            true => println!("Success"),
+            false => println!("Error: {:#?}", res.error),
         }
+        Ok(())
+    }
+
+    /// Uploads a PNG image to the channel
+    pub async fn upload_file(
+        &self,
+        channel: &str,
+        filename: &str,
+        title: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut slack_header = HeaderMap::new();
+        let slack_auth_value = format!("Bearer {}", &self.token);
+        slack_header.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&slack_auth_value).unwrap(),
+        );
+        let file = Part::bytes(bytes)
+            .file_name(filename.to_string())
+            .mime_str("image/png")?;
+        let form = Form::new()
+            .text("channels", channel.to_string())
+            .text("filename", filename.to_string())
+            .text("title", title.to_string())
+            .part("file", file);
+        let client = reqwest::Client::new();
+        let res = client
+            .post(Self::URL_FILES_UPLOAD)
+            .multipart(form)
+            .headers(slack_header)
+            .send()
+            .await?
+            .json::<SlackResponse>()
+            .await?;
+        match res.ok {
+            true => println!("Success"),
+            false => println!("Error: {:#?}", res.error),
+        }
+        Ok(())
     }
 }
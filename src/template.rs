@@ -0,0 +1,289 @@
+use crate::locale::{Labels, Locale};
+use crate::values::Duration;
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::*;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError, TemplateError,
+};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Values given to the summary template
+#[derive(Serialize, Debug, Clone)]
+pub struct SummaryContext {
+    /// Words translated by the locale
+    pub labels: Labels,
+    pub begin: NaiveDate,
+    pub end: NaiveDate,
+    /// Total hours of everyone
+    pub total: String,
+    /// Change of the total from the previous period, which is empty unless `comparison`
+    pub delta: String,
+    /// True if shares and the ranking of projects are shown
+    pub shares: bool,
+    /// True if bar charts are shown
+    pub bars: bool,
+    /// True if hours are compared with the previous period
+    pub comparison: bool,
+    /// True if billable and non-billable hours and billed amounts are shown
+    pub billing: bool,
+    /// Users grouped by workspace, or the only unnamed workspace unless grouped
+    pub workspaces: Vec<WorkspaceContext>,
+    /// Projects of everyone ranked by hours
+    pub projects: Vec<RankingContext>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WorkspaceContext {
+    /// Empty unless users are grouped by workspace
+    pub name: String,
+    pub total: String,
+    pub users: Vec<UserContext>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UserContext {
+    pub name: String,
+    pub total: String,
+    /// Change of the total from the previous period, e.g. "+3.5h ▲, +41%"
+    pub delta: String,
+    pub projects: Vec<ProjectContext>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProjectContext {
+    pub name: String,
+    /// Name padded to the widest name of the user's projects
+    pub label: String,
+    pub time: String,
+    /// Bar chart of the time, which is empty unless `bars`
+    pub bar: String,
+    #[serde(skip)]
+    pub duration: Duration,
+    /// Share of the user's total, e.g. "45%"
+    pub share: String,
+    /// Change from the previous period, e.g. "+3.5h ▲, +41%"
+    pub delta: String,
+    /// True if the user has a running time entry of the project
+    pub running: bool,
+    /// Billable hours, which are empty unless `billing`
+    pub billable: String,
+    /// Non-billable hours, which are empty unless `billing`
+    pub non_billable: String,
+    /// Billed amounts per currency, e.g. "12.50 USD", which are empty if nothing is billed
+    pub amount: String,
+    pub descriptions: Vec<DescriptionContext>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RankingContext {
+    /// 1 for the project of the most hours
+    pub rank: usize,
+    pub name: String,
+    /// Name padded to the widest name of the ranked projects
+    pub label: String,
+    pub time: String,
+    /// Bar chart of the time, which is empty unless `bars`
+    pub bar: String,
+    #[serde(skip)]
+    pub duration: Duration,
+    /// Share of all the tracked time, e.g. "45%"
+    pub share: String,
+    /// Change from the previous period, e.g. "+3.5h ▲, +41%"
+    pub delta: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DescriptionContext {
+    pub name: String,
+    pub time: String,
+}
+
+/// Handlebars template of the summary message
+///
+/// The template is validated when it is loaded, so that errors point at its line before fetching reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryTemplate {
+    name: String,
+    source: String,
+}
+
+impl Default for SummaryTemplate {
+    /// Returns the built-in template
+    fn default() -> Self {
+        SummaryTemplate {
+            name: "summary.hbs".to_string(),
+            source: include_str!("../templates/summary.hbs").to_string(),
+        }
+    }
+}
+
+impl SummaryTemplate {
+    /// Parses `source` and renders sample values with it to find errors
+    pub fn new(name: &str, source: &str) -> Result<Self, String> {
+        let template = SummaryTemplate {
+            name: name.to_string(),
+            source: source.to_string(),
+        };
+        let mut registry = template.registry()?;
+        registry.set_strict_mode(true);
+        registry
+            .render(&template.name, &Self::sample_context())
+            .map_err(|e| template.render_error(e))?;
+        Ok(template)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::new(&path.display().to_string(), &source)
+    }
+
+    pub fn render(&self, context: &SummaryContext) -> Result<String, String> {
+        self.registry()?
+            .render(&self.name, context)
+            .map_err(|e| self.render_error(e))
+    }
+
+    fn registry(&self) -> Result<Handlebars<'static>, String> {
+        let mut registry = Handlebars::new();
+        // Slack messages are not HTML
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.register_helper("date", Box::new(Self::date_helper));
+        registry
+            .register_template_string(&self.name, &self.source)
+            .map_err(|e| self.template_error(e))?;
+        Ok(registry)
+    }
+
+    /// `{{date begin "%Y/%m/%d"}}` formats a date by strftime, or by `labels.date_format` without format
+    fn date_helper(
+        h: &Helper,
+        _: &Handlebars,
+        ctx: &Context,
+        _: &mut RenderContext,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let date = h
+            .param(0)
+            .and_then(|p| p.value().as_str())
+            .and_then(|s| s.parse::<NaiveDate>().ok())
+            .ok_or_else(|| RenderError::new("date: the first parameter must be a date"))?;
+        let format = h
+            .param(1)
+            .map(|p| p.value())
+            .or_else(|| ctx.data().pointer("/labels/date_format"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("%Y-%m-%d");
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(RenderError::new(format!("date: invalid format {}", format)));
+        }
+        out.write(&date.format(format).to_string())?;
+        Ok(())
+    }
+
+    fn template_error(&self, e: TemplateError) -> String {
+        match (e.line_no, e.column_no) {
+            (Some(line), Some(column)) => {
+                format!("{}:{}:{}: {}", self.name, line, column, e.reason)
+            }
+            _ => format!("{}: {}", self.name, e.reason),
+        }
+    }
+
+    fn render_error(&self, e: RenderError) -> String {
+        match (e.line_no, e.column_no) {
+            (Some(line), Some(column)) => format!("{}:{}:{}: {}", self.name, line, column, e.desc),
+            _ => format!("{}: {}", self.name, e.desc),
+        }
+    }
+
+    /// Returns values which reach every block of a template
+    fn sample_context() -> SummaryContext {
+        let date = NaiveDate::from_ymd(2020, 12, 1);
+        SummaryContext {
+            labels: *Locale::default().labels(),
+            begin: date,
+            end: date,
+            total: "1".to_string(),
+            delta: "+1h ▲".to_string(),
+            shares: true,
+            bars: true,
+            comparison: true,
+            billing: false,
+            workspaces: vec![WorkspaceContext {
+                name: "Workspace".to_string(),
+                total: "1".to_string(),
+                users: vec![UserContext {
+                    name: "User".to_string(),
+                    total: "1".to_string(),
+                    delta: "+1h ▲".to_string(),
+                    projects: vec![ProjectContext {
+                        name: "Project".to_string(),
+                        label: "Project".to_string(),
+                        time: "1".to_string(),
+                        bar: "█".to_string(),
+                        duration: Duration::new(3_600_000),
+                        share: "100%".to_string(),
+                        delta: "+1h ▲".to_string(),
+                        running: true,
+                        billable: String::new(),
+                        non_billable: String::new(),
+                        amount: String::new(),
+                        descriptions: vec![DescriptionContext {
+                            name: "Description".to_string(),
+                            time: "1".to_string(),
+                        }],
+                    }],
+                }],
+            }],
+            projects: vec![RankingContext {
+                rank: 1,
+                name: "Project".to_string(),
+                label: "Project".to_string(),
+                time: "1".to_string(),
+                bar: "█".to_string(),
+                duration: Duration::new(3_600_000),
+                share: "100%".to_string(),
+                delta: "+1h ▲".to_string(),
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_must_point_at_the_line_of_syntax_errors() {
+        let actual = SummaryTemplate::new("custom.hbs", "Report\n{{#each workspaces}}\n{{/if}}");
+
+        let error = actual.unwrap_err();
+        assert!(error.starts_with("custom.hbs:3:"), "{}", error);
+    }
+
+    #[test]
+    fn new_must_reject_unknown_values() {
+        let actual = SummaryTemplate::new(
+            "custom.hbs",
+            "{{#each workspaces}}\n{{#each users}}{{hours}}{{/each}}{{/each}}",
+        );
+
+        let error = actual.unwrap_err();
+        assert!(error.starts_with("custom.hbs:2:"), "{}", error);
+    }
+
+    #[test]
+    fn render_must_format_dates_without_escaping() {
+        let template =
+            SummaryTemplate::new("custom.hbs", "{{date begin \"%m/%d\"}} <{{total}}h>").unwrap();
+        let context = SummaryContext {
+            total: "1.5".to_string(),
+            ..SummaryTemplate::sample_context()
+        };
+
+        assert_eq!(template.render(&context).unwrap(), "12/01 <1.5h>");
+    }
+}
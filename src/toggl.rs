@@ -1,50 +1,254 @@
-use crate::values::{Duration, Project, ProjectRecords, User};
+use crate::cache::{CacheKey, ResponseCache};
+use crate::catalog::Catalog;
+use crate::filter::ReportFilter;
+use crate::values::{
+    Amount, Billing, BillingRecords, Currency, Description, DescriptionRecords, Duration, Money,
+    Period, Project, ProjectRecords, User,
+};
 use chrono::prelude::*;
+use chrono_tz::Tz;
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::{thread, time};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time;
 
 #[derive(Deserialize, Debug)]
-struct TogglSummaryResponse {
-    data: Vec<TogglSummary>,
+pub struct TogglSummaryResponse {
+    pub data: Vec<TogglSummary>,
 }
 
 #[derive(Deserialize, Debug)]
-struct TogglSummary {
-    id: u64,
-    title: TogglSummaryTitle,
-    items: Vec<TogglItem>,
+pub struct TogglSummary {
+    pub id: u64,
+    pub title: TogglSummaryTitle,
+    pub items: Vec<TogglItem>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct TogglSummaryTitle {
-    user: User,
+pub struct TogglSummaryTitle {
+    pub user: User,
 }
 
 #[derive(Deserialize, Debug)]
-struct TogglItem {
-    title: TogglItemTitle,
-    time: Duration,
+pub struct TogglItem {
+    pub title: TogglItemTitle,
+    pub time: Duration,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TogglItemTitle {
+    pub project: Project,
 }
 
 #[derive(Deserialize, Debug)]
-struct TogglItemTitle {
-    project: Project,
+pub struct TogglDetailResponse {
+    pub total_count: u64,
+    pub per_page: u64,
+    pub data: Vec<TogglDetail>,
 }
 
 #[derive(Deserialize, Debug)]
-struct TogglDetailResponse {
+struct TogglRawDetailResponse {
     total_count: u64,
     per_page: u64,
-    data: Vec<TogglDetail>,
+    data: Vec<serde_json::Value>,
 }
 
 #[derive(Deserialize, Debug)]
-struct TogglDetail {
-    description: String,
+struct TogglEntryStart {
     start: DateTime<FixedOffset>,
-    dur: Duration,
-    user: User,
-    project: Project,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct TogglDetail {
+    pub description: String,
+    /// Name of the Toggl task, which is null if the entry has no task
+    #[serde(default)]
+    pub task: Option<String>,
+    pub start: DateTime<FixedOffset>,
+    pub dur: Duration,
+    pub user: User,
+    pub project: Project,
+    #[serde(default)]
+    pub uid: Option<u64>,
+    /// Project ID, which is null if the entry has no project
+    #[serde(default)]
+    pub pid: Option<u64>,
+    #[serde(default)]
+    pub client: Option<String>,
+    #[serde(default)]
+    pub is_billable: bool,
+    /// Billed amount in `cur`, which is null for non-billable entries
+    #[serde(default)]
+    pub billable: Option<f64>,
+    #[serde(default)]
+    pub cur: Option<String>,
+    /// True if the entry is still running, which never comes from Reports API
+    #[serde(default)]
+    pub running: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct TogglDashboardResponse {
+    /// Recent time entries of the workspace users, which is null if there are none
+    #[serde(default)]
+    activity: Option<Vec<TogglActivity>>,
+}
+
+/// Recent time entry of a workspace user fetched from the dashboard of Toggl Track API
+#[derive(Clone, Deserialize, Debug)]
+pub struct TogglActivity {
+    pub user_id: u64,
+    #[serde(default)]
+    pub project_id: Option<u64>,
+    /// Negative start time in Unix seconds while the entry is running
+    pub duration: i64,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl TogglActivity {
+    pub fn is_running(&self) -> bool {
+        self.duration < 0
+    }
+
+    /// Converts this running entry to `TogglDetail` whose duration is the elapsed time until `now`
+    pub fn to_detail(
+        &self,
+        user: User,
+        project: Project,
+        billable: bool,
+        now: DateTime<Utc>,
+    ) -> TogglDetail {
+        let start = Utc
+            .timestamp(-self.duration, 0)
+            .with_timezone(&FixedOffset::east(0));
+        let elapsed = (now - start.with_timezone(&Utc)).num_milliseconds();
+        TogglDetail {
+            description: self.description.clone().unwrap_or_default(),
+            task: None,
+            start,
+            dur: Duration::new(elapsed.max(0) as u64),
+            user,
+            project,
+            uid: Some(self.user_id),
+            pid: self.project_id,
+            client: None,
+            is_billable: billable,
+            billable: None,
+            cur: None,
+            running: true,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct TogglWorkspaceUser {
+    pub id: u64,
+    pub email: String,
+    pub fullname: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TogglWorkspaceResponse {
+    data: TogglWorkspace,
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct TogglWorkspace {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct TogglProject {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub cid: Option<u64>,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub billable: bool,
+    /// Colour shown in Toggl, e.g. "#06aaf5"
+    #[serde(default)]
+    pub hex_color: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct TogglClient {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct TogglTag {
+    pub id: u64,
+    pub name: String,
+}
+
+impl TogglDetail {
+    /// Returns the billing information of this entry
+    pub fn billing(&self) -> Billing {
+        if !self.is_billable {
+            return Billing::new(Duration::new(0), self.dur, Money::default());
+        }
+        let amount = match (&self.cur, self.billable) {
+            (Some(cur), Some(units)) => {
+                Money::single(Currency::new(cur), Amount::from_units(units))
+            }
+            _ => Money::default(),
+        };
+        Billing::new(self.dur, Duration::new(0), amount)
+    }
+
+    /// Returns the dates and durations of this entry in `timezone`
+    ///
+    /// If `timezone` is None, the offset returned from Toggl API is used.
+    /// If `split_at_midnight` is false, the whole duration belongs to the start date.
+    pub fn durations_by_date(
+        &self,
+        timezone: Option<&Tz>,
+        split_at_midnight: bool,
+    ) -> Vec<(NaiveDate, Duration)> {
+        match (timezone, split_at_midnight) {
+            (Some(tz), true) => Self::split_at_midnight(self.start.with_timezone(tz), self.dur),
+            (Some(tz), false) => {
+                vec![(self.start.with_timezone(tz).date().naive_local(), self.dur)]
+            }
+            (None, true) => Self::split_at_midnight(self.start, self.dur),
+            (None, false) => vec![(self.start.naive_local().date(), self.dur)],
+        }
+    }
+
+    fn split_at_midnight<T: TimeZone>(
+        start: DateTime<T>,
+        dur: Duration,
+    ) -> Vec<(NaiveDate, Duration)> {
+        let timezone = start.timezone();
+        let mut current = start;
+        let mut remaining = dur.value as i64;
+        let mut durations = vec![];
+        loop {
+            let date = current.naive_local().date();
+            let next_midnight = timezone
+                .from_local_datetime(&(date + chrono::Duration::days(1)).and_hms(0, 0, 0))
+                .earliest();
+            let until_midnight = next_midnight
+                .as_ref()
+                .map(|m| (m.clone() - current.clone()).num_milliseconds())
+                .unwrap_or(remaining);
+            if remaining <= until_midnight {
+                durations.push((date, Duration::new(remaining as u64)));
+                return durations;
+            }
+            durations.push((date, Duration::new(until_midnight as u64)));
+            remaining -= until_midnight;
+            current = next_midnight.expect("midnight exists if the entry continues");
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
@@ -54,56 +258,356 @@ pub struct RecordKey {
     pub date: NaiveDate,
 }
 
+/// Summary and time entries of a workspace in a report period
+#[derive(Clone, Debug)]
+pub struct TogglReport {
+    pub summary: ProjectRecords,
+    pub details: Vec<TogglDetail>,
+    /// Running entries, which are included in `summary` and `details` as well
+    pub running_details: Vec<TogglDetail>,
+}
+
+impl TogglReport {
+    /// Renames users, merging the durations of the users who get the same name
+    pub fn rename_users(self, names: &HashMap<User, User>) -> Self {
+        let rename = |u: &User| names.get(u).cloned().unwrap_or_else(|| u.clone());
+        let rename_details = |details: Vec<TogglDetail>| {
+            details
+                .into_iter()
+                .map(|d| TogglDetail {
+                    user: rename(&d.user),
+                    ..d
+                })
+                .collect()
+        };
+        let mut summary = ProjectRecords::new(BTreeMap::new());
+        for (u, project_times) in self.summary.value {
+            for (p, dur) in project_times {
+                summary.add(rename(&u), p, dur);
+            }
+        }
+        TogglReport {
+            summary,
+            details: rename_details(self.details),
+            running_details: rename_details(self.running_details),
+        }
+    }
+
+    /// Merges the reports of several workspaces into one
+    pub fn merge(reports: Vec<TogglReport>) -> Self {
+        let mut merged = TogglReport {
+            summary: ProjectRecords::new(BTreeMap::new()),
+            details: vec![],
+            running_details: vec![],
+        };
+        for report in reports {
+            for (u, project_times) in report.summary.value {
+                for (p, dur) in project_times {
+                    merged.summary.add(u.clone(), p, dur);
+                }
+            }
+            merged.details.extend(report.details);
+            merged.running_details.extend(report.running_details);
+        }
+        merged
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct TogglAccessor {
     pub token: String,
     pub workspace: String,
     pub email: String,
+    /// If true, only billable time entries are reported
+    pub billable_only: bool,
+    /// Users, projects and clients to report, which are passed to Toggl API where possible
+    pub filter: ReportFilter,
+    /// Timezone to bucket the entries by date, which is the offset returned from Toggl API if None
+    pub timezone: Option<Tz>,
+    /// If true, entries crossing midnight are divided into each date
+    pub split_at_midnight: bool,
+    /// If true, the running entry is included in the report
+    pub include_running: bool,
+    /// Cache of Reports API responses, which is not used if None
+    pub cache: Option<ResponseCache>,
+    /// Directory to write the raw Reports API responses, which are not written if None
+    pub save_dir: Option<PathBuf>,
+    /// If true, the summary comes from Summary API instead of being derived from the time entries
+    pub toggl_summary: bool,
+    /// If true, warns about the differences between Summary API and the time entries
+    pub check_summary: bool,
 }
 
 impl TogglAccessor {
     const SUMMARY_REPORT_URL: &'static str = "https://api.track.toggl.com/reports/api/v2/summary";
     const DETAILED_REPORT_URL: &'static str = "https://api.track.toggl.com/reports/api/v2/details";
+    const DASHBOARD_URL: &'static str = "https://api.track.toggl.com/api/v8/dashboard";
+    const WORKSPACES_URL: &'static str = "https://api.track.toggl.com/api/v8/workspaces";
+
+    /// Fetches the summary and time entries in `period`, applying the filters
+    ///
+    /// The running entry is measured until `now`.
+    pub async fn fetch_report(
+        &self,
+        period: &Period,
+        now: DateTime<Utc>,
+    ) -> Result<TogglReport, Box<dyn std::error::Error>> {
+        let date_from = period.begin.format("%Y-%m-%d").to_string();
+        let date_to = period.end.format("%Y-%m-%d").to_string();
+        let summary = if self.toggl_summary || self.check_summary {
+            Some(self.fetch_summary_report(&date_from, &date_to).await?)
+        } else {
+            None
+        };
+        // Toggl API reports dates in the timezone of the Toggl profile,
+        // so the entries are fetched with a margin and clipped by the dates in `timezone`.
+        let margin = match self.timezone {
+            Some(_) => chrono::Duration::days(1),
+            None => chrono::Duration::zero(),
+        };
+        let details = self
+            .fetch_details(
+                &(period.begin - margin).format("%Y-%m-%d").to_string(),
+                &(period.end + margin).format("%Y-%m-%d").to_string(),
+            )
+            .await?;
+        let running_details = if self.include_running {
+            self.fetch_running_details(now).await?
+        } else {
+            vec![]
+        };
+        Ok(self.build_report(summary, details, running_details, period))
+    }
+
+    /// Clips the entries by `period`, applies the filters and sums them up into the summary
+    ///
+    /// `details` may contain entries out of `period`, while `summary` from Summary API must not.
+    /// `summary` is used only if `toggl_summary` or `check_summary` is set.
+    pub fn build_report(
+        &self,
+        summary: Option<ProjectRecords>,
+        details: Vec<TogglDetail>,
+        running_details: Vec<TogglDetail>,
+        period: &Period,
+    ) -> TogglReport {
+        let timezone = self.timezone.as_ref();
+        let mut details = Self::retain_period(details, period, timezone, self.split_at_midnight);
+        let running_details =
+            Self::retain_period(running_details, period, timezone, self.split_at_midnight);
+        details.extend(running_details.iter().cloned());
+
+        // the catalog is built before filtering to look up the excluded ones as well
+        let catalog = Catalog::from_details(&details);
+        let details = self.filter.retain_details(details);
+        let running_details = self.filter.retain_details(running_details);
+        let derived = Self::convert_details_to_project_records(
+            &details,
+            timezone,
+            self.split_at_midnight,
+            period,
+        );
+        let summary = match summary {
+            Some(summary) => {
+                let mut summary = self.filter.retain_project_records(summary, &catalog);
+                // Reports API excludes running entries, so they are added to the summary here
+                for d in &running_details {
+                    let dur = d
+                        .durations_by_date(timezone, self.split_at_midnight)
+                        .into_iter()
+                        .filter(|(date, _)| period.contains(date))
+                        .fold(Duration::new(0), |acc, (_, dur)| acc + dur);
+                    summary.add(d.user.clone(), d.project.clone(), dur);
+                }
+                if self.check_summary {
+                    self.warn_summary_differences(&summary, &derived);
+                }
+                if self.toggl_summary {
+                    summary
+                } else {
+                    derived
+                }
+            }
+            None => derived,
+        };
+        TogglReport {
+            summary,
+            details,
+            running_details,
+        }
+    }
+
+    /// Prints the users and projects whose durations differ between Summary API and the time entries
+    ///
+    /// They may differ when entries are edited between the requests,
+    /// or when `timezone` is not the timezone of the Toggl profile.
+    fn warn_summary_differences(&self, summary: &ProjectRecords, derived: &ProjectRecords) {
+        let hours = |dur: &Duration| dur.value as f64 / 3_600_000.0;
+        let workspace = match self.workspace.as_str() {
+            "" => String::new(),
+            w => format!(" in workspace {}", w),
+        };
+        for (u, p, in_summary, in_details) in summary.differences(derived) {
+            eprintln!(
+                "Warning: {user} / {project}{workspace} is {summary:.2}h in Summary API but {details:.2}h in the time entries",
+                user = u.to_string(),
+                project = p.to_string(),
+                workspace = workspace,
+                summary = hours(&in_summary),
+                details = hours(&in_details),
+            );
+        }
+    }
+
+    async fn fetch_track_api<T: DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let res = reqwest::Client::new()
+            .get(url)
+            .basic_auth(&self.token, Some("api_token"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await?;
+        Ok(res)
+    }
+
+    /// Fetches all the workspaces of the token owner from Toggl Track API
+    pub async fn fetch_workspaces(
+        &self,
+    ) -> Result<Vec<TogglWorkspace>, Box<dyn std::error::Error>> {
+        self.fetch_track_api(Self::WORKSPACES_URL).await
+    }
+
+    /// Fetches the workspace from Toggl Track API
+    pub async fn fetch_workspace(&self) -> Result<TogglWorkspace, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}", Self::WORKSPACES_URL, self.workspace);
+        let res: TogglWorkspaceResponse = self.fetch_track_api(&url).await?;
+        Ok(res.data)
+    }
+
+    /// Fetches the users of the workspace from Toggl Track API
+    pub async fn fetch_workspace_users(
+        &self,
+    ) -> Result<Vec<TogglWorkspaceUser>, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}/users", Self::WORKSPACES_URL, self.workspace);
+        self.fetch_track_api(&url).await
+    }
+
+    /// Fetches the projects of the workspace from Toggl Track API
+    pub async fn fetch_projects(&self) -> Result<Vec<TogglProject>, Box<dyn std::error::Error>> {
+        // Toggl API returns null instead of an empty list
+        let url = format!("{}/{}/projects", Self::WORKSPACES_URL, self.workspace);
+        let res: Option<Vec<TogglProject>> = self.fetch_track_api(&url).await?;
+        Ok(res.unwrap_or_default())
+    }
+
+    /// Fetches the clients of the workspace from Toggl Track API
+    pub async fn fetch_clients(&self) -> Result<Vec<TogglClient>, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}/clients", Self::WORKSPACES_URL, self.workspace);
+        let res: Option<Vec<TogglClient>> = self.fetch_track_api(&url).await?;
+        Ok(res.unwrap_or_default())
+    }
+
+    /// Fetches the tags of the workspace from Toggl Track API
+    pub async fn fetch_tags(&self) -> Result<Vec<TogglTag>, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}/tags", Self::WORKSPACES_URL, self.workspace);
+        let res: Option<Vec<TogglTag>> = self.fetch_track_api(&url).await?;
+        Ok(res.unwrap_or_default())
+    }
 
     /// Fetches summary report from Toggl API and convert it to HashMap
+    ///
+    /// Summary API sums up the whole period without days, so the response is cached by the period.
     pub async fn fetch_summary_report(
         &self,
         date_from: &str,
         date_to: &str,
     ) -> Result<ProjectRecords, Box<dyn std::error::Error>> {
-        let res = self.fetch_summary(date_from, date_to).await?;
-        let project_time_by_user = Self::convert_summary_to_hashmap(&res);
-        Ok(project_time_by_user)
+        let end = NaiveDate::parse_from_str(date_to, "%Y-%m-%d")?;
+        let key = self.cache_key("summary", &format!("since={}", date_from), end);
+        let body = match self.cached(&key) {
+            Some(body) => body,
+            None => {
+                let body = self.fetch_summary(date_from, date_to).await?;
+                self.store(&key, &body)?;
+                body
+            }
+        };
+        let res = serde_json::from_str::<TogglSummaryResponse>(&body)?;
+        Ok(Self::convert_summary_to_hashmap(&res))
     }
 
-    /// Fetches summary report from Toggl API
+    /// Fetches the raw summary report from Toggl API
     async fn fetch_summary(
         &self,
         date_from: &str,
         date_to: &str,
-    ) -> Result<TogglSummaryResponse, Box<dyn std::error::Error>> {
+    ) -> Result<String, Box<dyn std::error::Error>> {
         let url = Self::SUMMARY_REPORT_URL;
         let client = reqwest::Client::new();
-        // let res: TogglSummaryResponse = client
-        let res = client
+        let body = client
             .get(url)
             .basic_auth(&self.token, Some("api_token"))
             .query(&[
                 ("workspace_id", &self.workspace as &str),
-                ("since", &date_from),
-                ("until", &date_to),
+                ("since", date_from),
+                ("until", date_to),
                 ("user_agent", &self.email),
                 ("grouping", "users"),
                 ("subgrouping", "projects"),
             ])
+            .query(&self.filter_query())
             .send()
+            .await?
+            .error_for_status()?
+            .text()
             .await?;
-        // println!("res = {:?}", res.text().await?);
-        let json = res.json::<TogglSummaryResponse>().await?;
-        Ok(json)
+        self.save(&format!("summary_{}_{}.json", date_from, date_to), &body)?;
+        Ok(body)
+    }
+
+    /// Returns the cache key of a Reports API request which is narrowed down by `filter_query`
+    fn cache_key(&self, endpoint: &str, query: &str, day: NaiveDate) -> CacheKey {
+        let filter_query = self
+            .filter_query()
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .join("&");
+        CacheKey {
+            workspace: self.workspace.clone(),
+            endpoint: endpoint.to_string(),
+            query: format!("{}&{}", query, filter_query),
+            day,
+        }
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<String> {
+        self.cache.as_ref()?.get(key)
+    }
+
+    /// Writes the raw response to `save_dir` to replay it offline
+    fn save(&self, name: &str, body: &str) -> std::io::Result<()> {
+        match &self.save_dir {
+            Some(dir) => {
+                let dir = dir.join(&self.workspace);
+                std::fs::create_dir_all(&dir)?;
+                std::fs::write(dir.join(name), body)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn store(&self, key: &CacheKey, body: &str) -> std::io::Result<()> {
+        match &self.cache {
+            Some(cache) => cache.put(key, body),
+            None => Ok(()),
+        }
     }
 
     /// Converts summary report fetched from Toggl API to HashMap
-    fn convert_summary_to_hashmap(res: &TogglSummaryResponse) -> ProjectRecords {
+    pub fn convert_summary_to_hashmap(res: &TogglSummaryResponse) -> ProjectRecords {
         let records = res
             .data
             .iter()
@@ -119,48 +623,91 @@ impl TogglAccessor {
         ProjectRecords::new(records)
     }
 
-    /// Fetches detailed report from Toggl API and convert it to Vec
-    pub async fn fetch_detailed_report(
-        &self,
-        date_from: &str,
-        date_to: &str,
-    ) -> Result<Vec<(RecordKey, Duration)>, Box<dyn std::error::Error>> {
-        let details = self.fetch_details(date_from, date_to).await?;
-        let dur_time_by_project_user_date: Vec<(RecordKey, Duration)> =
-            Self::convert_details_to_vec(&details);
-        Ok(dur_time_by_project_user_date)
+    /// Returns query parameters to narrow down the reported time entries
+    fn filter_query(&self) -> Vec<(&'static str, String)> {
+        let mut query = self.filter.query();
+        if self.billable_only {
+            query.push(("billable", "yes".to_string()));
+        }
+        query
     }
 
     /// Fetches detailed report from Toggl API
     ///
-    /// TODO: rewrite paging process
-    async fn fetch_details(
+    /// The entries are cached per day, and only the days missing in the cache are fetched.
+    pub async fn fetch_details(
         &self,
         date_from: &str,
         date_to: &str,
     ) -> Result<Vec<TogglDetail>, Box<dyn std::error::Error>> {
+        let begin = NaiveDate::parse_from_str(date_from, "%Y-%m-%d")?;
+        let end = NaiveDate::parse_from_str(date_to, "%Y-%m-%d")?;
+        let mut entries_by_day: BTreeMap<NaiveDate, Vec<serde_json::Value>> = BTreeMap::new();
+        let mut missing_ranges: Vec<(NaiveDate, NaiveDate)> = vec![];
+        for day in begin.iter_days().take_while(|d| *d <= end) {
+            match self.cached(&self.cache_key("details", "", day)) {
+                Some(body) => {
+                    entries_by_day.insert(day, serde_json::from_str(&body)?);
+                }
+                None => match missing_ranges.last_mut() {
+                    Some((_, last)) if last.succ() == day => *last = day,
+                    _ => missing_ranges.push((day, day)),
+                },
+            }
+        }
+        for (first, last) in missing_ranges {
+            let mut fetched: BTreeMap<NaiveDate, Vec<serde_json::Value>> = first
+                .iter_days()
+                .take_while(|d| *d <= last)
+                .map(|d| (d, vec![]))
+                .collect();
+            let entries = self
+                .fetch_detail_entries(
+                    &first.format("%Y-%m-%d").to_string(),
+                    &last.format("%Y-%m-%d").to_string(),
+                )
+                .await?;
+            // Reports API filters entries by the start date in the timezone of the Toggl profile
+            for entry in entries {
+                let day = TogglEntryStart::deserialize(&entry)?
+                    .start
+                    .naive_local()
+                    .date();
+                if let Some(entries_of_day) = fetched.get_mut(&day) {
+                    entries_of_day.push(entry);
+                }
+            }
+            for (day, entries_of_day) in fetched {
+                if self.cache.is_some() {
+                    let body = serde_json::to_string(&entries_of_day)?;
+                    self.store(&self.cache_key("details", "", day), &body)?;
+                }
+                entries_by_day.insert(day, entries_of_day);
+            }
+        }
+        let details = entries_by_day
+            .into_iter()
+            .flat_map(|(_, entries_of_day)| entries_of_day)
+            .map(serde_json::from_value::<TogglDetail>)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|d| !self.billable_only || d.is_billable)
+            .collect();
+        Ok(details)
+    }
+
+    /// Fetches the raw time entries of detailed report from Toggl API, following the pages
+    async fn fetch_detail_entries(
+        &self,
+        date_from: &str,
+        date_to: &str,
+    ) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
         let url = Self::DETAILED_REPORT_URL;
         let client = reqwest::Client::new();
-        let first_res = client
-            .get(url)
-            .basic_auth(&self.token, Some("api_token"))
-            .query(&[
-                ("workspace_id", &self.workspace as &str),
-                ("since", &date_from),
-                ("until", &date_to),
-                ("user_agent", &self.email),
-            ])
-            .send()
-            .await?
-            .json::<TogglDetailResponse>()
-            .await?;
-        let max_page = (first_res.total_count as f64 / first_res.per_page as f64).ceil() as u64;
-        let mut buf: Vec<TogglDetailResponse> = Vec::new();
-        buf.push(first_res);
-        for page in 2..=max_page {
-            // to avoid rapid accesses
-            thread::sleep(time::Duration::from_millis(2000));
-            let tmp_res = client
+        let mut entries = vec![];
+        let mut page: u64 = 1;
+        loop {
+            let body = client
                 .get(url)
                 .basic_auth(&self.token, Some("api_token"))
                 .query(&[
@@ -170,39 +717,259 @@ impl TogglAccessor {
                     ("user_agent", &self.email),
                     ("page", &page.to_string()),
                 ])
+                .query(&self.filter_query())
                 .send()
                 .await?
-                .json::<TogglDetailResponse>()
+                .error_for_status()?
+                .text()
                 .await?;
-            buf.push(tmp_res);
+            self.save(
+                &format!("details_{}_{}_{}.json", date_from, date_to, page),
+                &body,
+            )?;
+            let res = serde_json::from_str::<TogglRawDetailResponse>(&body)?;
+            let max_page = (res.total_count as f64 / res.per_page as f64).ceil() as u64;
+            entries.extend(res.data);
+            if page >= max_page {
+                return Ok(entries);
+            }
+            page += 1;
+            // to avoid rapid accesses
+            tokio::time::delay_for(time::Duration::from_millis(2000)).await;
         }
-        let details: Vec<TogglDetail> = buf.into_iter().flat_map(|res| res.data).collect();
+    }
+
+    /// Fetches the running time entries of the workspace users from the dashboard of Toggl Track API
+    ///
+    /// Running entries are measured until `now`, and those of non-billable projects are dropped if `billable_only`.
+    pub async fn fetch_running_details(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<TogglDetail>, Box<dyn std::error::Error>> {
+        let url = format!("{}/{}", Self::DASHBOARD_URL, self.workspace);
+        let dashboard: TogglDashboardResponse = self.fetch_track_api(&url).await?;
+        let activity = dashboard.activity.unwrap_or_default();
+        if !activity.iter().any(|a| a.is_running()) {
+            return Ok(vec![]);
+        }
+        let users = self.fetch_workspace_users().await?;
+        let projects = self.fetch_projects().await?;
+        let details = Self::convert_activity_to_running_details(&activity, &users, &projects, now)
+            .into_iter()
+            .filter(|d| !self.billable_only || d.is_billable)
+            .collect();
         Ok(details)
     }
 
+    /// Converts the running entries of `activity` to `TogglDetail`, one per user at most
+    pub fn convert_activity_to_running_details(
+        activity: &[TogglActivity],
+        users: &[TogglWorkspaceUser],
+        projects: &[TogglProject],
+        now: DateTime<Utc>,
+    ) -> Vec<TogglDetail> {
+        let names: HashMap<u64, &str> = users.iter().map(|u| (u.id, u.fullname.as_str())).collect();
+        let projects: HashMap<u64, &TogglProject> = projects.iter().map(|p| (p.id, p)).collect();
+        activity
+            .iter()
+            .filter(|a| a.is_running())
+            .unique_by(|a| a.user_id)
+            .filter_map(|a| {
+                let user = User::new(*names.get(&a.user_id)?);
+                let project = a.project_id.and_then(|pid| projects.get(&pid));
+                Some(a.to_detail(
+                    user,
+                    Project::new(project.map(|p| p.name.clone())),
+                    project.map(|p| p.billable).unwrap_or(false),
+                    now,
+                ))
+            })
+            .collect()
+    }
+
     /// Converts detailed report fetched from Toggl API to Vec
-    fn convert_details_to_vec(data: &Vec<TogglDetail>) -> Vec<(RecordKey, Duration)> {
+    pub fn convert_details_to_vec(data: &[TogglDetail]) -> Vec<(RecordKey, Duration)> {
+        Self::convert_details_to_vec_in_timezone(data, None, false)
+    }
+
+    /// Converts detailed report fetched from Toggl API to Vec, bucketing entries by dates in `timezone`
+    ///
+    /// See `TogglDetail::durations_by_date` for `timezone` and `split_at_midnight`.
+    pub fn convert_details_to_vec_in_timezone(
+        data: &[TogglDetail],
+        timezone: Option<&Tz>,
+        split_at_midnight: bool,
+    ) -> Vec<(RecordKey, Duration)> {
         data.iter()
-            .map(|d| {
-                let u = d.user.clone();
-                let p = d.project.clone();
-                let start = d.start.naive_local().date();
-                let dur = d.dur;
-                let record_key = RecordKey {
-                    user: u,
-                    project: p,
-                    date: start,
-                };
-                (record_key, dur)
+            .flat_map(|d| {
+                d.durations_by_date(timezone, split_at_midnight)
+                    .into_iter()
+                    .map(move |(date, dur)| {
+                        let record_key = RecordKey {
+                            user: d.user.clone(),
+                            project: d.project.clone(),
+                            date,
+                        };
+                        (record_key, dur)
+                    })
+            })
+            .collect()
+    }
+
+    /// Sums up durations in `period` per user and project, sorting projects by name
+    ///
+    /// See `TogglDetail::durations_by_date` for `timezone` and `split_at_midnight`.
+    pub fn convert_details_to_project_records(
+        data: &[TogglDetail],
+        timezone: Option<&Tz>,
+        split_at_midnight: bool,
+        period: &Period,
+    ) -> ProjectRecords {
+        let mut durations: BTreeMap<User, BTreeMap<Project, Duration>> = BTreeMap::new();
+        for (k, dur) in Self::convert_details_to_vec_in_timezone(data, timezone, split_at_midnight)
+        {
+            if period.contains(&k.date) {
+                let total = durations
+                    .entry(k.user)
+                    .or_default()
+                    .entry(k.project)
+                    .or_default();
+                *total = *total + dur;
+            }
+        }
+        ProjectRecords::new(
+            durations
+                .into_iter()
+                .map(|(u, by_project)| (u, by_project.into_iter().collect()))
+                .collect(),
+        )
+    }
+
+    /// Removes time entries which have no duration in `period`
+    ///
+    /// See `TogglDetail::durations_by_date` for `timezone` and `split_at_midnight`.
+    pub fn retain_period(
+        data: Vec<TogglDetail>,
+        period: &Period,
+        timezone: Option<&Tz>,
+        split_at_midnight: bool,
+    ) -> Vec<TogglDetail> {
+        data.into_iter()
+            .filter(|d| {
+                d.durations_by_date(timezone, split_at_midnight)
+                    .iter()
+                    .any(|(date, _)| period.contains(date))
+            })
+            .collect()
+    }
+
+    /// Sums up durations per user, project and description, keeping the top `top_n` descriptions
+    ///
+    /// Near-identical descriptions are grouped by `Description::normalized`
+    /// and labelled with the original text which took the longest time.
+    pub fn convert_details_to_description_records(
+        data: &[TogglDetail],
+        top_n: usize,
+    ) -> DescriptionRecords {
+        type Originals = BTreeMap<Description, Duration>;
+        let mut grouped: BTreeMap<User, BTreeMap<Project, BTreeMap<Description, Originals>>> =
+            BTreeMap::new();
+        for d in data {
+            let description = Description::new(d.task.clone(), d.description.clone());
+            let originals = grouped
+                .entry(d.user.clone())
+                .or_default()
+                .entry(d.project.clone())
+                .or_default()
+                .entry(description.normalized())
+                .or_default();
+            let dur = originals.remove(&description).unwrap_or_default() + d.dur;
+            originals.insert(description, dur);
+        }
+
+        let records = grouped
+            .into_iter()
+            .map(|(u, by_project)| {
+                let by_project = by_project
+                    .into_iter()
+                    .map(|(p, by_normalized)| {
+                        let descriptions = by_normalized
+                            .into_iter()
+                            .filter_map(|(_, originals)| {
+                                let total = originals
+                                    .values()
+                                    .fold(Duration::default(), |acc, dur| acc + *dur);
+                                let label = originals
+                                    .into_iter()
+                                    .max_by_key(|(_, dur)| dur.value)
+                                    .map(|(description, _)| description);
+                                label.map(|l| (l, total))
+                            })
+                            .sorted_by(|(d1, t1), (d2, t2)| {
+                                t2.value.cmp(&t1.value).then_with(|| d1.cmp(d2))
+                            })
+                            .take(top_n)
+                            .collect();
+                        (p, descriptions)
+                    })
+                    .collect();
+                (u, by_project)
+            })
+            .collect();
+        DescriptionRecords::new(records)
+    }
+
+    /// Sums up billable and non-billable durations and billed amounts per user and project
+    pub fn convert_details_to_billing_records(data: &[TogglDetail]) -> BillingRecords {
+        let mut billings: BTreeMap<User, BTreeMap<Project, Billing>> = BTreeMap::new();
+        for d in data {
+            let by_project = billings.entry(d.user.clone()).or_default();
+            let billing = by_project.remove(&d.project).unwrap_or_default() + d.billing();
+            by_project.insert(d.project.clone(), billing);
+        }
+        let records = billings
+            .into_iter()
+            .map(|(u, by_project)| (u, by_project.into_iter().collect()))
+            .collect();
+        BillingRecords::new(records)
+    }
+
+    /// Returns the renames of users per workspace to unify the names of the same email
+    ///
+    /// The name in the earliest workspace wins.
+    pub fn unify_user_names(
+        users_by_workspace: &[Vec<TogglWorkspaceUser>],
+    ) -> Vec<HashMap<User, User>> {
+        let mut names_by_email: HashMap<&str, &str> = HashMap::new();
+        for u in users_by_workspace.iter().flatten() {
+            names_by_email
+                .entry(u.email.as_str())
+                .or_insert_with(|| u.fullname.as_str());
+        }
+        users_by_workspace
+            .iter()
+            .map(|users| {
+                users
+                    .iter()
+                    .filter(|u| names_by_email[u.email.as_str()] != u.fullname)
+                    .map(|u| {
+                        (
+                            User::new(u.fullname.as_str()),
+                            User::new(names_by_email[u.email.as_str()]),
+                        )
+                    })
+                    .collect()
             })
             .collect()
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::inconsistent_digit_grouping)]
 mod tests {
     use super::*;
     #[test]
+    #[allow(clippy::useless_vec)]
     fn convert_summary_to_hashmap_must_work_well() {
         let user_name_1 = User::new("Alice");
         let user_name_2 = User::new("Bob");
@@ -290,30 +1057,54 @@ mod tests {
         let data = vec![
             TogglDetail {
                 description: desc.clone(),
+                task: None,
                 start: "2020-12-01T10:00:00+09:00"
                     .parse::<DateTime<FixedOffset>>()
                     .expect(""),
                 dur: dur1,
                 user: user1.clone(),
                 project: project.clone(),
+                uid: None,
+                pid: None,
+                client: None,
+                is_billable: false,
+                billable: None,
+                cur: None,
+                running: false,
             },
             TogglDetail {
                 description: desc.clone(),
+                task: None,
                 start: "2020-12-01T20:00:00+09:00"
                     .parse::<DateTime<FixedOffset>>()
                     .expect(""),
                 dur: dur2,
                 user: user1.clone(),
                 project: project.clone(),
+                uid: None,
+                pid: None,
+                client: None,
+                is_billable: false,
+                billable: None,
+                cur: None,
+                running: false,
             },
             TogglDetail {
                 description: desc.clone(),
+                task: None,
                 start: "2020-12-01T15:00:00+09:00"
                     .parse::<DateTime<FixedOffset>>()
                     .expect(""),
                 dur: dur3,
                 user: user2.clone(),
                 project: project.clone(),
+                uid: None,
+                pid: None,
+                client: None,
+                is_billable: false,
+                billable: None,
+                cur: None,
+                running: false,
             },
         ];
 
@@ -346,4 +1137,328 @@ mod tests {
         ];
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn convert_details_to_billing_records_must_sum_up_per_user_and_project() {
+        let user1 = User::new("Alice");
+        let user2 = User::new("Bob");
+        let project = Project::new(Some("ProjectA"));
+        let start = "2020-12-01T10:00:00+09:00"
+            .parse::<DateTime<FixedOffset>>()
+            .expect("");
+        let detail =
+            |user: &User, dur: u64, billable: Option<f64>, cur: Option<&str>| TogglDetail {
+                description: "".to_string(),
+                task: None,
+                start,
+                dur: Duration::new(dur),
+                user: user.clone(),
+                project: project.clone(),
+                uid: None,
+                pid: None,
+                client: None,
+                is_billable: billable.is_some(),
+                billable,
+                cur: cur.map(|c| c.to_string()),
+                running: false,
+            };
+
+        let data = vec![
+            detail(&user1, 100, Some(10.0), Some("USD")),
+            detail(&user1, 200, Some(2.5), Some("USD")),
+            detail(&user1, 400, None, None),
+            detail(&user2, 800, Some(1000.0), Some("JPY")),
+        ];
+
+        let actual = TogglAccessor::convert_details_to_billing_records(&data);
+        let expected = BillingRecords::new(
+            [
+                (
+                    user1.clone(),
+                    vec![(
+                        project.clone(),
+                        Billing::new(
+                            Duration::new(300),
+                            Duration::new(400),
+                            Money::single(Currency::new("USD"), Amount::new(1250)),
+                        ),
+                    )],
+                ),
+                (
+                    user2.clone(),
+                    vec![(
+                        project.clone(),
+                        Billing::new(
+                            Duration::new(800),
+                            Duration::new(0),
+                            Money::single(Currency::new("JPY"), Amount::new(100000)),
+                        ),
+                    )],
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn convert_details_to_description_records_must_keep_top_n_normalized_descriptions() {
+        let user = User::new("Alice");
+        let project = Project::new(Some("ProjectA"));
+        let start = "2020-12-01T10:00:00+09:00"
+            .parse::<DateTime<FixedOffset>>()
+            .expect("");
+        let detail = |task: Option<&str>, description: &str, dur: u64| TogglDetail {
+            description: description.to_string(),
+            task: task.map(|t| t.to_string()),
+            start,
+            dur: Duration::new(dur),
+            user: user.clone(),
+            project: project.clone(),
+            uid: None,
+            pid: None,
+            client: None,
+            is_billable: false,
+            billable: None,
+            cur: None,
+            running: false,
+        };
+
+        let data = vec![
+            detail(None, "ABC-1: Fix login bug", 100),
+            detail(None, "fix  login bug", 300),
+            detail(Some("Review"), "Code review", 250),
+            detail(None, "Meeting", 50),
+        ];
+
+        let actual = TogglAccessor::convert_details_to_description_records(&data, 2);
+        let expected = DescriptionRecords::new(
+            [(
+                user.clone(),
+                [(
+                    project.clone(),
+                    vec![
+                        (Description::new(None, "fix  login bug"), Duration::new(400)),
+                        (
+                            Description::new(Some("Review"), "Code review"),
+                            Duration::new(250),
+                        ),
+                    ],
+                )]
+                .iter()
+                .cloned()
+                .collect(),
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(actual, expected)
+    }
+
+    fn detail_starting_at(start: &str, dur: u64) -> TogglDetail {
+        TogglDetail {
+            description: "".to_string(),
+            task: None,
+            start: start.parse::<DateTime<FixedOffset>>().expect(""),
+            dur: Duration::new(dur),
+            user: User::new("Alice"),
+            project: Project::new(Some("ProjectA")),
+            uid: None,
+            pid: None,
+            client: None,
+            is_billable: false,
+            billable: None,
+            cur: None,
+            running: false,
+        }
+    }
+
+    #[test]
+    fn running_details_must_be_converted_from_running_activity_of_each_user() {
+        let activity: Vec<TogglActivity> = serde_json::from_str(
+            r#"[
+                {"user_id": 1, "project_id": 10, "duration": -1606784400, "description": "Review"},
+                {"user_id": 2, "project_id": null, "duration": -1606788000},
+                {"user_id": 2, "project_id": 10, "duration": 3600, "description": "Done"},
+                {"user_id": 3, "project_id": 10, "duration": 1800}
+            ]"#,
+        )
+        .unwrap();
+        let users = vec![
+            TogglWorkspaceUser {
+                id: 1,
+                email: "alice@example.com".to_string(),
+                fullname: "Alice".to_string(),
+            },
+            TogglWorkspaceUser {
+                id: 2,
+                email: "bob@example.com".to_string(),
+                fullname: "Bob".to_string(),
+            },
+        ];
+        let projects = vec![TogglProject {
+            id: 10,
+            name: "ProjectA".to_string(),
+            cid: None,
+            active: true,
+            billable: true,
+            hex_color: None,
+        }];
+        // 2020-12-01T01:00:00Z and 02:00:00Z are the starts of the running entries
+        let now = "2020-12-01T02:30:00Z".parse::<DateTime<Utc>>().expect("");
+
+        let actual =
+            TogglAccessor::convert_activity_to_running_details(&activity, &users, &projects, now);
+
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual[0].user, User::new("Alice"));
+        assert_eq!(actual[0].project, Project::new(Some("ProjectA")));
+        assert_eq!(actual[0].dur, Duration::new(90 * 60_000));
+        assert_eq!(actual[0].description, "Review");
+        assert!(actual[0].running);
+        assert!(actual[0].is_billable);
+        assert_eq!(actual[1].user, User::new("Bob"));
+        assert_eq!(actual[1].project, Project::new::<String>(None));
+        assert_eq!(actual[1].dur, Duration::new(30 * 60_000));
+        assert!(!actual[1].is_billable);
+    }
+
+    #[test]
+    fn durations_by_date_must_use_the_given_timezone() {
+        // 2020-12-02T08:30 in Tokyo is 2020-12-01T18:30 in New York
+        let detail = detail_starting_at("2020-12-02T08:30:00+09:00", 3600_000);
+        let tz: Tz = "America/New_York".parse().unwrap();
+
+        assert_eq!(
+            detail.durations_by_date(None, false),
+            vec![(NaiveDate::from_ymd(2020, 12, 2), Duration::new(3600_000))]
+        );
+        assert_eq!(
+            detail.durations_by_date(Some(&tz), false),
+            vec![(NaiveDate::from_ymd(2020, 12, 1), Duration::new(3600_000))]
+        );
+    }
+
+    #[test]
+    fn durations_by_date_must_split_entries_crossing_midnight() {
+        let detail = detail_starting_at("2020-12-01T23:30:00+09:00", (24 * 60 + 60) * 60_000);
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+
+        let actual = detail.durations_by_date(Some(&tz), true);
+        let expected = vec![
+            (NaiveDate::from_ymd(2020, 12, 1), Duration::new(30 * 60_000)),
+            (
+                NaiveDate::from_ymd(2020, 12, 2),
+                Duration::new(24 * 60 * 60_000),
+            ),
+            (NaiveDate::from_ymd(2020, 12, 3), Duration::new(30 * 60_000)),
+        ];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn convert_details_to_project_records_must_sum_up_durations_in_the_period() {
+        let period = Period::new(
+            NaiveDate::from_ymd(2020, 12, 1),
+            NaiveDate::from_ymd(2020, 12, 1),
+        );
+        let data = vec![
+            detail_starting_at("2020-12-01T09:00:00+09:00", 3600_000),
+            detail_starting_at("2020-12-01T23:30:00+09:00", 3600_000),
+            detail_starting_at("2020-12-02T09:00:00+09:00", 3600_000),
+        ];
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+
+        let actual =
+            TogglAccessor::convert_details_to_project_records(&data, Some(&tz), true, &period);
+        let expected = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![(Project::new(Some("ProjectA")), Duration::new(5400_000))],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn retain_period_must_keep_entries_which_have_duration_in_the_period() {
+        let period = Period::new(
+            NaiveDate::from_ymd(2020, 12, 2),
+            NaiveDate::from_ymd(2020, 12, 2),
+        );
+        let data = vec![
+            detail_starting_at("2020-12-01T23:30:00+09:00", 3600_000),
+            detail_starting_at("2020-12-03T00:30:00+09:00", 3600_000),
+        ];
+        let tz: Tz = "Asia/Tokyo".parse().unwrap();
+
+        assert_eq!(
+            TogglAccessor::retain_period(data, &period, Some(&tz), true).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn unify_user_names_must_rename_users_to_the_name_in_the_first_workspace() {
+        let user = |id: u64, email: &str, fullname: &str| TogglWorkspaceUser {
+            id,
+            email: email.to_string(),
+            fullname: fullname.to_string(),
+        };
+        let users_by_workspace = vec![
+            vec![user(1, "alice@example.com", "Alice")],
+            vec![
+                user(1, "alice@example.com", "alice"),
+                user(2, "bob@example.com", "Bob"),
+            ],
+        ];
+
+        let actual = TogglAccessor::unify_user_names(&users_by_workspace);
+        let expected: Vec<HashMap<User, User>> = vec![
+            HashMap::new(),
+            [(User::new("alice"), User::new("Alice"))]
+                .iter()
+                .cloned()
+                .collect(),
+        ];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn merge_must_sum_up_summaries_of_renamed_users() {
+        let project = Project::new(Some("ProjectA"));
+        let report = |user: &str| TogglReport {
+            summary: ProjectRecords::new(
+                [(User::new(user), vec![(project.clone(), Duration::new(100))])]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            ),
+            details: vec![],
+            running_details: vec![],
+        };
+        let names: HashMap<User, User> = [(User::new("alice"), User::new("Alice"))]
+            .iter()
+            .cloned()
+            .collect();
+
+        let actual =
+            TogglReport::merge(vec![report("Alice"), report("alice").rename_users(&names)]);
+        let expected = ProjectRecords::new(
+            [(
+                User::new("Alice"),
+                vec![(project.clone(), Duration::new(200))],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(actual.summary, expected)
+    }
 }
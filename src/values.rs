@@ -1,7 +1,7 @@
 use chrono::prelude::*;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::Add;
 
@@ -15,6 +15,7 @@ impl User {
             value: value.into(),
         }
     }
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         self.value.to_string()
     }
@@ -44,7 +45,27 @@ impl<'de> Deserialize<'de> for User {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Workspace {
+    pub value: String,
+}
+impl Workspace {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Workspace {
+            value: value.into(),
+        }
+    }
+}
+impl fmt::Display for Workspace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+/// Project serialized as its name, or null for time entries without a project
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
 pub struct Project {
     pub value: Option<ProjectValue>,
 }
@@ -58,6 +79,7 @@ impl Project {
         }
     }
 
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         self.to_owned()
             .value
@@ -67,6 +89,7 @@ impl Project {
 }
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
 pub struct ProjectValue {
     pub value: String,
 }
@@ -133,7 +156,7 @@ impl<'de> Deserialize<'de> for ProjectValue {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
 pub struct Duration {
     pub value: u64,
 }
@@ -176,13 +199,272 @@ impl<'de> Deserialize<'de> for Duration {
     }
 }
 
-#[derive(Debug)]
+/// Description of a time entry, optionally prefixed with its Toggl task
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Description {
+    pub task: Option<String>,
+    pub value: String,
+}
+impl Description {
+    const NONE_DESCRIPTION_LABEL: &'static str = "(no description)";
+
+    pub fn new<S: Into<String>>(task: Option<S>, value: S) -> Self {
+        Description {
+            task: task.map(|t| t.into()),
+            value: value.into(),
+        }
+    }
+
+    /// Returns the key to group near-identical descriptions
+    ///
+    /// Leading ticket prefixes (e.g. `ABC-123:`, `#123`, `[ABC-123]`) are removed,
+    /// whitespaces are collapsed and letters are lowercased.
+    /// e.g. "[ABC-12]  Fix Login" -> "fix login"
+    pub fn normalized(&self) -> Description {
+        let mut rest = self.value.trim();
+        loop {
+            let stripped = Self::strip_ticket_prefix(rest);
+            if stripped.len() == rest.len() {
+                break;
+            }
+            rest = stripped;
+        }
+        let value = rest
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase();
+        Description {
+            task: self.task.as_ref().map(|t| t.trim().to_lowercase()),
+            value,
+        }
+    }
+
+    /// Removes one leading ticket prefix and the separators after it
+    fn strip_ticket_prefix(text: &str) -> &str {
+        let (token, rest) = match text.strip_prefix('[') {
+            Some(inner) => match inner.find(']') {
+                Some(close) => (&inner[..close], &inner[close + 1..]),
+                None => return text,
+            },
+            None => {
+                let end = text
+                    .find(|c: char| c.is_whitespace() || c == ':')
+                    .unwrap_or(text.len());
+                (&text[..end], &text[end..])
+            }
+        };
+        if !Self::is_ticket(token) {
+            return text;
+        }
+        rest.trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '-')
+    }
+
+    /// Returns true if `token` looks like `#123` or `ABC-123`
+    fn is_ticket(token: &str) -> bool {
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        if let Some(number) = token.strip_prefix('#') {
+            return is_number(number);
+        }
+        match token.rfind('-') {
+            Some(hyphen) => {
+                let key = &token[..hyphen];
+                !key.is_empty()
+                    && key.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && key.chars().all(|c| c.is_ascii_alphanumeric())
+                    && is_number(&token[hyphen + 1..])
+            }
+            None => false,
+        }
+    }
+}
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = if self.value.trim().is_empty() {
+            Self::NONE_DESCRIPTION_LABEL
+        } else {
+            self.value.trim()
+        };
+        match &self.task {
+            Some(task) => write!(f, "[{}] {}", task, value),
+            None => f.write_str(value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Currency {
+    pub value: String,
+}
+impl Currency {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Currency {
+            value: value.into(),
+        }
+    }
+}
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+/// Amount of money in hundredths of a currency unit
+///
+/// Amounts are kept as integers so that they sum up without rounding errors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Amount {
+    pub value: u64,
+}
+impl Amount {
+    pub fn new(value: u64) -> Self {
+        Amount { value }
+    }
+
+    /// Converts an amount given in currency units (e.g. 12.5) to `Amount`
+    pub fn from_units(units: f64) -> Self {
+        Amount::new((units * 100.0).round() as u64)
+    }
+}
+impl Add for Amount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            value: self.value + other.value,
+        }
+    }
+}
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:02}", self.value / 100, self.value % 100)
+    }
+}
+
+/// Amounts of money per currency
+///
+/// Adding two `Money` sums up the amounts of the same currency only.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Money {
+    pub value: BTreeMap<Currency, Amount>,
+}
+impl Money {
+    pub fn new(value: BTreeMap<Currency, Amount>) -> Self {
+        Money { value }
+    }
+
+    pub fn single(currency: Currency, amount: Amount) -> Self {
+        let mut value = BTreeMap::new();
+        value.insert(currency, amount);
+        Money { value }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+}
+impl Add for Money {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut value = self.value;
+        for (currency, amount) in other.value {
+            let sum = value.get(&currency).copied().unwrap_or_default() + amount;
+            value.insert(currency, sum);
+        }
+        Self { value }
+    }
+}
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = self
+            .value
+            .iter()
+            .map(|(currency, amount)| format!("{} {}", amount, currency))
+            .collect::<Vec<String>>()
+            .join(", ");
+        f.write_str(&text)
+    }
+}
+
+/// Billable and non-billable durations and the billed amount
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Billing {
+    pub billable: Duration,
+    pub non_billable: Duration,
+    pub amount: Money,
+}
+impl Billing {
+    pub fn new(billable: Duration, non_billable: Duration, amount: Money) -> Self {
+        Billing {
+            billable,
+            non_billable,
+            amount,
+        }
+    }
+}
+impl Add for Billing {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            billable: self.billable + other.billable,
+            non_billable: self.non_billable + other.non_billable,
+            amount: self.amount + other.amount,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Period {
-    begin: NaiveDate,
-    end: NaiveDate,
+    pub begin: NaiveDate,
+    pub end: NaiveDate,
 }
+impl Period {
+    pub fn new(begin: NaiveDate, end: NaiveDate) -> Self {
+        Period { begin, end }
+    }
 
-#[derive(Debug, Eq, PartialEq)]
+    /// Parses the period from the given dates in `YYYY-MM-DD`
+    pub fn parse(date_from: &str, date_to: &str) -> Result<Self, String> {
+        let parse_date = |date: &str| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|e| format!("Invalid date {}: {}", date, e))
+        };
+        let begin = parse_date(date_from)?;
+        let end = parse_date(date_to)?;
+        if begin > end {
+            return Err(format!("{} is after {}", begin, end));
+        }
+        Ok(Period::new(begin, end))
+    }
+
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        &self.begin <= date && date <= &self.end
+    }
+
+    /// Returns the period of the same length just before this period
+    ///
+    /// Periods of whole months are compared with the same number of whole months,
+    /// e.g. 2020-12-01 to 2020-12-31 with 2020-11-01 to 2020-11-30.
+    pub fn previous(&self) -> Period {
+        let first_of_month = |year: i32, month: i32| {
+            let months = year * 12 + month - 1;
+            NaiveDate::from_ymd(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+        };
+        let next_day = self.end.succ();
+        if self.begin.day() == 1 && next_day.day() == 1 {
+            let months = (next_day.year() - self.begin.year()) * 12 + next_day.month() as i32
+                - self.begin.month() as i32;
+            let begin = first_of_month(self.begin.year(), self.begin.month() as i32 - months);
+            return Period::new(begin, self.begin.pred());
+        }
+        let days = self.end - self.begin + chrono::Duration::days(1);
+        Period::new(self.begin - days, self.begin.pred())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ProjectRecords {
     pub value: BTreeMap<User, Vec<(Project, Duration)>>,
 }
@@ -190,6 +472,67 @@ impl ProjectRecords {
     pub fn new(value: BTreeMap<User, Vec<(Project, Duration)>>) -> Self {
         ProjectRecords { value }
     }
+
+    /// Adds `dur` to the duration of `user` and `project`, appending the project if it is new
+    pub fn add(&mut self, user: User, project: Project, dur: Duration) {
+        let project_times = self.value.entry(user).or_default();
+        match project_times.iter_mut().find(|(p, _)| p == &project) {
+            Some((_, d)) => *d = *d + dur,
+            None => project_times.push((project, dur)),
+        }
+    }
+
+    /// Returns users and projects whose durations differ, with the durations in `self` and `other`
+    ///
+    /// Missing users and projects are regarded as 0.
+    pub fn differences(&self, other: &ProjectRecords) -> Vec<(User, Project, Duration, Duration)> {
+        let lookup = |records: &ProjectRecords, u: &User, p: &Project| {
+            records
+                .value
+                .get(u)
+                .and_then(|project_times| project_times.iter().find(|(q, _)| q == p))
+                .map(|(_, dur)| *dur)
+                .unwrap_or_default()
+        };
+        let keys: BTreeSet<(&User, &Project)> = self
+            .value
+            .iter()
+            .chain(other.value.iter())
+            .flat_map(|(u, project_times)| project_times.iter().map(move |(p, _)| (u, p)))
+            .collect();
+        keys.into_iter()
+            .map(|(u, p)| {
+                (
+                    u.clone(),
+                    p.clone(),
+                    lookup(self, u, p),
+                    lookup(other, u, p),
+                )
+            })
+            .filter(|(_, _, a, b)| a != b)
+            .collect()
+    }
+}
+
+/// Top descriptions per user and project, sorted by duration descendingly
+#[derive(Debug, Eq, PartialEq)]
+pub struct DescriptionRecords {
+    pub value: BTreeMap<User, BTreeMap<Project, Vec<(Description, Duration)>>>,
+}
+impl DescriptionRecords {
+    pub fn new(value: BTreeMap<User, BTreeMap<Project, Vec<(Description, Duration)>>>) -> Self {
+        DescriptionRecords { value }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct BillingRecords {
+    pub value: BTreeMap<User, Vec<(Project, Billing)>>,
+}
+impl BillingRecords {
+    pub fn new(value: BTreeMap<User, Vec<(Project, Billing)>>) -> Self {
+        BillingRecords { value }
+    }
 }
 
 #[cfg(test)]
@@ -242,6 +585,206 @@ mod tests {
         assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn project_must_be_serialized_as_it_is_deserialized() {
+        let projects = vec![Project::new(Some("ProjectA")), Project::new(None::<&str>)];
+        let actual = serde_json::to_string(&projects).unwrap();
+        assert_eq!(actual, r#"["ProjectA",null]"#);
+        assert_eq!(
+            serde_json::from_str::<Vec<Project>>(&actual).unwrap(),
+            projects
+        );
+    }
+
+    #[test]
+    fn project_records_differences_must_list_mismatches_including_missing_ones() {
+        let alice = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let records1 = ProjectRecords::new(
+            [(
+                alice.clone(),
+                vec![
+                    (project1.clone(), Duration::new(100)),
+                    (project2.clone(), Duration::new(200)),
+                ],
+            )]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        let records2 = ProjectRecords::new(
+            [
+                (alice.clone(), vec![(project1.clone(), Duration::new(150))]),
+                (
+                    User::new("Bob"),
+                    vec![(project2.clone(), Duration::new(10))],
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+
+        let actual = records1.differences(&records2);
+        let expected = vec![
+            (
+                alice.clone(),
+                project1.clone(),
+                Duration::new(100),
+                Duration::new(150),
+            ),
+            (
+                alice.clone(),
+                project2.clone(),
+                Duration::new(200),
+                Duration::new(0),
+            ),
+            (
+                User::new("Bob"),
+                project2.clone(),
+                Duration::new(0),
+                Duration::new(10),
+            ),
+        ];
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn project_records_add_must_sum_up_existing_projects_and_append_new_ones() {
+        let user = User::new("Alice");
+        let project1 = Project::new(Some("ProjectA"));
+        let project2 = Project::new(Some("ProjectB"));
+        let mut records = ProjectRecords::new(
+            [(user.clone(), vec![(project1.clone(), Duration::new(100))])]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+
+        records.add(user.clone(), project1.clone(), Duration::new(10));
+        records.add(user.clone(), project2.clone(), Duration::new(20));
+        records.add(User::new("Bob"), project2.clone(), Duration::new(30));
+
+        let expected = ProjectRecords::new(
+            [
+                (
+                    user.clone(),
+                    vec![
+                        (project1.clone(), Duration::new(110)),
+                        (project2.clone(), Duration::new(20)),
+                    ],
+                ),
+                (
+                    User::new("Bob"),
+                    vec![(project2.clone(), Duration::new(30))],
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        assert_eq!(records, expected)
+    }
+
+    #[test]
+    fn money_must_sum_up_amounts_per_currency() {
+        let usd = Currency::new("USD");
+        let jpy = Currency::new("JPY");
+        let m1 = Money::single(usd.clone(), Amount::new(1050));
+        let m2 = Money::single(jpy.clone(), Amount::new(100000));
+        let m3 = Money::single(usd.clone(), Amount::new(25));
+
+        let actual = m1 + m2 + m3;
+        let expected = Money::new(
+            [(usd, Amount::new(1075)), (jpy, Amount::new(100000))]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn money_must_be_displayed_per_currency() {
+        let money = Money::single(Currency::new("USD"), Amount::from_units(12.5))
+            + Money::single(Currency::new("JPY"), Amount::from_units(1000.0));
+
+        assert_eq!(money.to_string(), "1000.00 JPY, 12.50 USD")
+    }
+
+    #[test]
+    fn normalized_description_must_ignore_case_whitespaces_and_ticket_prefixes() {
+        let expected = Description::new(None, "fix login bug");
+        for value in &[
+            "Fix login bug",
+            "  fix   Login bug ",
+            "ABC-123: Fix login bug",
+            "[ABC-123] fix login bug",
+            "#42 - fix login bug",
+            "[ABC-1] DEF-2: fix login bug",
+        ] {
+            let actual = Description::new(None, *value).normalized();
+            assert_eq!(actual, expected, "input: {}", value);
+        }
+    }
+
+    #[test]
+    fn normalized_description_must_keep_prefixes_which_are_not_tickets() {
+        let actual = Description::new(None, "[web] Fix login bug").normalized();
+        let expected = Description::new(None, "[web] fix login bug");
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn normalized_description_must_distinguish_tasks() {
+        let d1 = Description::new(Some("Review"), "fix login bug");
+        let d2 = Description::new(None, "fix login bug");
+
+        assert_ne!(d1.normalized(), d2.normalized())
+    }
+
+    #[test]
+    fn period_must_be_parsed_from_dates() {
+        let actual = Period::parse("2020-12-01", " 2020-12-09");
+        let expected = Period::new(
+            NaiveDate::from_ymd(2020, 12, 1),
+            NaiveDate::from_ymd(2020, 12, 9),
+        );
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn previous_must_be_as_long_as_the_period() {
+        let period = |begin: (i32, u32, u32), end: (i32, u32, u32)| {
+            Period::new(
+                NaiveDate::from_ymd(begin.0, begin.1, begin.2),
+                NaiveDate::from_ymd(end.0, end.1, end.2),
+            )
+        };
+
+        assert_eq!(
+            period((2020, 12, 7), (2020, 12, 13)).previous(),
+            period((2020, 11, 30), (2020, 12, 6))
+        );
+        assert_eq!(
+            period((2021, 3, 1), (2021, 3, 31)).previous(),
+            period((2021, 2, 1), (2021, 2, 28))
+        );
+        assert_eq!(
+            period((2021, 1, 1), (2021, 2, 28)).previous(),
+            period((2020, 11, 1), (2020, 12, 31))
+        );
+    }
+
+    #[test]
+    fn period_must_not_be_parsed_from_invalid_dates() {
+        assert!(Period::parse("2020/12/01", "2020-12-09").is_err());
+        assert!(Period::parse("yesterday", "2020-12-09").is_err());
+        assert!(Period::parse("2020-12-09", "2020-12-01").is_err());
+    }
+
     #[test]
     fn this_empty_json_must_be_deserialized_as_empty_project() {
         let json = r#"{}"#;